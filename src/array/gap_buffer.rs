@@ -0,0 +1,254 @@
+use crate::array::allocate_heap;
+use std::ops::{Index, IndexMut};
+
+/// 配列の途中に空き(ギャップ)を持たせ、ギャップの位置での挿入と削除をO(1)にしたもの
+/// カーソルの近くだけを編集するエディタのバッファ向け
+///
+/// a: 本体、a[..gs]とa[ge..]に要素が入っている
+/// gs: ギャップの始まり(カーソルの位置)
+/// ge: ギャップの終わり
+#[derive(Debug, Default)]
+pub struct GapBuffer<T> {
+    a: Box<[T]>,
+    gs: usize,
+    ge: usize,
+}
+
+impl<T> GapBuffer<T> {
+    /// 配列長
+    pub fn len(&self) -> usize {
+        self.a.len()
+    }
+
+    /// 内部要素の数
+    pub fn size(&self) -> usize {
+        self.a.len() - self.gap_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size() == 0
+    }
+
+    /// ギャップの位置
+    pub fn gap(&self) -> usize {
+        self.gs
+    }
+
+    fn gap_len(&self) -> usize {
+        self.ge - self.gs
+    }
+
+    /// 位置iの要素の、aでの位置
+    fn physical(&self, i: usize) -> usize {
+        if i < self.gs {
+            i
+        } else {
+            i + self.gap_len()
+        }
+    }
+
+    /// ギャップをiの前に動かす
+    /// ギャップの大きさではなく、動かす距離に比例する時間がかかる
+    pub fn move_gap_to(&mut self, i: usize) {
+        assert!(i <= self.size(), "index out of bounds");
+        let g = self.gap_len();
+        if i < self.gs {
+            for j in (i..self.gs).rev() {
+                self.a.swap(j, j + g);
+            }
+        } else {
+            for j in self.gs..i {
+                self.a.swap(j, j + g);
+            }
+        }
+        self.ge = i + g;
+        self.gs = i;
+    }
+
+    /// ギャップの前と後ろの要素
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        (&self.a[..self.gs], &self.a[self.ge..])
+    }
+
+    /// ギャップを末尾に動かして、要素を1つの連続したスライスにする
+    pub fn make_contiguous(&mut self) -> &mut [T] {
+        self.move_gap_to(self.size());
+        &mut self.a[..self.gs]
+    }
+}
+
+impl<T> GapBuffer<T>
+where
+    T: Default + Clone,
+{
+    pub fn new(n: usize) -> Self {
+        Self {
+            a: allocate_heap(n),
+            gs: 0,
+            ge: n,
+        }
+    }
+
+    /// 内部要素の数が多すぎたとき、少なすぎたときにメモリ上に確保する配列長を変更する
+    /// ギャップの前後の要素をそれぞれ新しい配列の先頭と末尾に移す
+    pub fn resize(&mut self) {
+        let n = self.size();
+        let new_length = std::cmp::max(n * 2, 1);
+        let mut b = allocate_heap(new_length);
+        let tail = self.a.len() - self.ge;
+        b[..self.gs].clone_from_slice(&self.a[..self.gs]);
+        b[new_length - tail..].clone_from_slice(&self.a[self.ge..]);
+
+        self.ge = new_length - tail;
+        let _old_a = std::mem::replace(&mut self.a, b);
+    }
+
+    /// ギャップの位置に入れてカーソルを進める
+    pub fn insert(&mut self, x: T) {
+        if self.gs == self.ge {
+            self.resize();
+        }
+        self.a[self.gs] = x;
+        self.gs += 1;
+    }
+
+    /// ギャップの直前の要素を消す(バックスペース)
+    pub fn delete_before(&mut self) -> Option<T> {
+        if self.gs == 0 {
+            return None;
+        }
+        self.gs -= 1;
+        let x = std::mem::take(&mut self.a[self.gs]);
+        self.shrink();
+        Some(x)
+    }
+
+    /// ギャップの直後の要素を消す(デリート)
+    pub fn delete_after(&mut self) -> Option<T> {
+        if self.ge == self.a.len() {
+            return None;
+        }
+        let x = std::mem::take(&mut self.a[self.ge]);
+        self.ge += 1;
+        self.shrink();
+        Some(x)
+    }
+
+    fn shrink(&mut self) {
+        if self.len() >= 3 * self.size() {
+            self.resize();
+        }
+    }
+
+    /// ArrayStackと同じく位置iに入れる、ギャップはiの直後に来る
+    pub fn add(&mut self, i: usize, x: T) {
+        self.move_gap_to(std::cmp::min(i, self.size()));
+        self.insert(x);
+    }
+
+    /// 位置iの要素を消す、ギャップはiに来る
+    pub fn remove(&mut self, i: usize) -> T {
+        assert!(i < self.size(), "index out of bounds");
+        self.move_gap_to(i);
+        self.delete_after().unwrap()
+    }
+
+    pub fn to_vec(&self) -> Vec<T> {
+        let (front, back) = self.as_slices();
+        let mut v = front.to_vec();
+        v.extend_from_slice(back);
+        v
+    }
+}
+
+impl<T> From<&[T]> for GapBuffer<T>
+where
+    T: Default + Clone,
+{
+    /// ギャップは末尾に置く
+    fn from(xs: &[T]) -> Self {
+        let mut buf = Self::new(std::cmp::max(xs.len() * 2, 1));
+        buf.a[..xs.len()].clone_from_slice(xs);
+        buf.gs = xs.len();
+        buf
+    }
+}
+
+impl<T> Index<usize> for GapBuffer<T> {
+    type Output = T;
+    fn index(&self, index: usize) -> &Self::Output {
+        assert!(index < self.size(), "index out of bounds");
+        &self.a[self.physical(index)]
+    }
+}
+
+impl<T> IndexMut<usize> for GapBuffer<T> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        assert!(index < self.size(), "index out of bounds");
+        let i = self.physical(index);
+        &mut self.a[i]
+    }
+}
+
+#[cfg(test)]
+mod test_gap_buffer {
+    use super::*;
+    use crate::rng::Rng;
+
+    #[test]
+    fn test_edit() {
+        let mut buf: GapBuffer<char> = GapBuffer::new(2);
+        for c in "hello world".chars() {
+            buf.insert(c);
+        }
+        assert_eq!(buf.size(), 11);
+        assert_eq!(buf.gap(), 11);
+
+        // "hello"の後ろに移って", "を書き、空白を消す
+        buf.move_gap_to(5);
+        buf.insert(',');
+        assert_eq!(buf.delete_after(), Some(' '));
+        buf.insert(' ');
+        assert_eq!(buf.to_vec().into_iter().collect::<String>(), "hello, world");
+        assert_eq!(buf[7], 'w');
+        buf[7] = 'W';
+
+        buf.move_gap_to(0);
+        assert_eq!(buf.delete_before(), None);
+        assert_eq!(buf.delete_after(), Some('h'));
+        buf.insert('H');
+        let (front, back) = buf.as_slices();
+        assert_eq!((front.len(), back.len()), (1, 11));
+        assert_eq!(
+            buf.make_contiguous().iter().collect::<String>(),
+            "Hello, World"
+        );
+        assert_eq!(buf.gap(), 12);
+    }
+
+    #[test]
+    fn test_add_remove() {
+        let mut buf = GapBuffer::from(&[0, 1, 2, 3][..]);
+        let mut v = vec![0, 1, 2, 3];
+        let mut rng = Rng::with_seed(1);
+        for i in 0..300 {
+            let j = rng.gen_range(v.len() + 1);
+            if i % 3 == 2 && j < v.len() {
+                assert_eq!(buf.remove(j), v.remove(j));
+            } else {
+                buf.add(j, i);
+                v.insert(j, i);
+            }
+        }
+        assert_eq!(buf.to_vec(), v);
+        for (i, x) in v.iter().enumerate() {
+            assert_eq!(buf[i], *x);
+        }
+
+        while buf.size() > 2 {
+            buf.remove(0);
+        }
+        // 少なくなったら縮む
+        assert!(buf.len() < 3 * 2 + 1);
+    }
+}
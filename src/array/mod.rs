@@ -1,5 +1,6 @@
 pub mod deque;
 pub mod dual_array_deque;
+pub mod gap_buffer;
 pub mod queue;
 pub mod rootish_array_stack;
 pub mod stack;
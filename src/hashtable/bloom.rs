@@ -0,0 +1,426 @@
+use crate::array::allocate_heap;
+use crate::hashtable::hash::{splitmix64, HashState};
+use std::hash::Hash;
+
+/// ハッシュ関数の数の上限(偽陽性率2^-64に当たる)
+pub const MAX_HASHES: usize = 64;
+
+/// ビット数(カウンタの数)の上限
+pub const MAX_BITS: usize = 1 << 32;
+
+/// 期待する要素数nと偽陽性率pからビット数mとハッシュ関数の数kを決める
+/// m = -n ln p / (ln 2)^2, k = (m / n) ln 2
+/// pは0より大きく1より小さいこと、mはMAX_BITSで頭打ちにする
+pub fn optimal_params(n: usize, p: f64) -> (usize, usize) {
+    assert!(
+        p > 0. && p < 1.,
+        "false positive rate must be in (0, 1), got {}",
+        p
+    );
+    let n = std::cmp::max(n, 1) as f64;
+    let ln2 = std::f64::consts::LN_2;
+    let m = (-n * p.ln() / (ln2 * ln2))
+        .ceil()
+        .clamp(1., MAX_BITS as f64);
+    let k = (m / n * ln2).round().clamp(1., MAX_HASHES as f64);
+    (m as usize, k as usize)
+}
+
+/// ダブルハッシング g_i(x) = h1(x) + i * h2(x) mod m
+fn indices<T: Hash + ?Sized>(
+    state: &HashState,
+    m: usize,
+    k: usize,
+    x: &T,
+) -> impl Iterator<Item = usize> {
+    let h1 = state.hash(x);
+    let h2 = splitmix64(&mut h1.clone()) | 1;
+    (0..k as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % m as u64) as usize)
+}
+
+fn read_u64(bytes: &[u8], at: usize) -> Option<u64> {
+    let b = bytes.get(at..at + 8)?;
+    let mut a = [0; 8];
+    a.copy_from_slice(b);
+    Some(u64::from_le_bytes(a))
+}
+
+/// シリアライズの先頭: m, k, n, ハッシュのキー(k0, k1)
+const HEADER_LEN: usize = 40;
+
+fn write_header(out: &mut Vec<u8>, m: usize, k: usize, n: usize, state: &HashState) {
+    let (k0, k1) = state.keys();
+    for v in [m as u64, k as u64, n as u64, k0, k1] {
+        out.extend_from_slice(&v.to_le_bytes());
+    }
+}
+
+fn read_header(bytes: &[u8]) -> Option<(usize, usize, usize, HashState)> {
+    let m = read_u64(bytes, 0)? as usize;
+    let k = read_u64(bytes, 8)? as usize;
+    let n = read_u64(bytes, 16)? as usize;
+    let state = HashState::with_keys(read_u64(bytes, 24)?, read_u64(bytes, 32)?);
+    if m == 0 || m > MAX_BITS || k == 0 || k > MAX_HASHES {
+        return None;
+    }
+    Some((m, k, n, state))
+}
+
+/// m: ビット数
+/// k: ハッシュ関数の数
+/// n: 追加した回数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BloomFilter {
+    bits: Box<[u64]>,
+    m: usize,
+    k: usize,
+    n: usize,
+    state: HashState,
+}
+
+impl BloomFilter {
+    /// 要素数nのときに偽陽性率がpになる大きさで作る
+    pub fn new(n: usize, p: f64) -> Self {
+        let (m, k) = optimal_params(n, p);
+        Self::with_params(m, k, HashState::new())
+    }
+
+    pub fn with_seed(n: usize, p: f64, seed: u64) -> Self {
+        let (m, k) = optimal_params(n, p);
+        Self::with_params(m, k, HashState::with_seed(seed))
+    }
+
+    pub fn with_params(m: usize, k: usize, state: HashState) -> Self {
+        assert!(m > 0 && m <= MAX_BITS && k > 0 && k <= MAX_HASHES);
+        Self {
+            bits: allocate_heap(m.div_ceil(64)),
+            m,
+            k,
+            n: 0,
+            state,
+        }
+    }
+
+    /// ビット数
+    pub fn len(&self) -> usize {
+        self.m
+    }
+
+    pub fn hashes(&self) -> usize {
+        self.k
+    }
+
+    /// 追加した回数(重複も数える)
+    pub fn size(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|&w| w == 0)
+    }
+
+    pub fn add<T: Hash + ?Sized>(&mut self, x: &T) {
+        for i in indices(&self.state, self.m, self.k, x) {
+            self.bits[i / 64] |= 1 << (i % 64);
+        }
+        self.n += 1;
+    }
+
+    /// falseなら確実に入っていない、trueなら偽陽性の可能性がある
+    pub fn contains<T: Hash + ?Sized>(&self, x: &T) -> bool {
+        indices(&self.state, self.m, self.k, x).all(|i| self.bits[i / 64] & (1 << (i % 64)) != 0)
+    }
+
+    /// 今のn回の追加に対する偽陽性率の見積もり (1 - e^{-kn/m})^k
+    pub fn false_positive_rate(&self) -> f64 {
+        let k = self.k as f64;
+        (1. - (-k * self.n as f64 / self.m as f64).exp()).powf(k)
+    }
+
+    /// m, k, ハッシュのキーが同じならビット演算で合成できる
+    pub fn is_compatible(&self, other: &Self) -> bool {
+        self.m == other.m && self.k == other.k && self.state == other.state
+    }
+
+    pub fn union(&self, other: &Self) -> Option<Self> {
+        let mut c = self.combine(other, |a, b| a | b)?;
+        c.n = self.n + other.n;
+        Some(c)
+    }
+
+    pub fn intersect(&self, other: &Self) -> Option<Self> {
+        let mut c = self.combine(other, |a, b| a & b)?;
+        c.n = std::cmp::min(self.n, other.n);
+        Some(c)
+    }
+
+    fn combine(&self, other: &Self, f: impl Fn(u64, u64) -> u64) -> Option<Self> {
+        if !self.is_compatible(other) {
+            return None;
+        }
+        let mut c = self.clone();
+        for (a, &b) in c.bits.iter_mut().zip(other.bits.iter()) {
+            *a = f(*a, b);
+        }
+        Some(c)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(HEADER_LEN + 8 * self.bits.len());
+        write_header(&mut out, self.m, self.k, self.n, &self.state);
+        for w in self.bits.iter() {
+            out.extend_from_slice(&w.to_le_bytes());
+        }
+        out
+    }
+
+    /// 壊れた見出しで大きな配列を確保しないよう、長さを確かめてから作る
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (m, k, n, state) = read_header(bytes)?;
+        let len = m.div_ceil(64).checked_mul(8)?.checked_add(HEADER_LEN)?;
+        if bytes.len() != len {
+            return None;
+        }
+        let mut f = Self::with_params(m, k, state);
+        for i in 0..f.bits.len() {
+            f.bits[i] = read_u64(bytes, HEADER_LEN + 8 * i)?;
+        }
+        f.n = n;
+        Some(f)
+    }
+}
+
+/// 各位置にビットの代わりにカウンタを持ち、削除できるようにしたもの
+/// カウンタが255で飽和したら、以降はその位置を減らさない
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CountingBloomFilter {
+    counters: Box<[u8]>,
+    m: usize,
+    k: usize,
+    n: usize,
+    state: HashState,
+}
+
+impl CountingBloomFilter {
+    pub fn new(n: usize, p: f64) -> Self {
+        let (m, k) = optimal_params(n, p);
+        Self::with_params(m, k, HashState::new())
+    }
+
+    pub fn with_seed(n: usize, p: f64, seed: u64) -> Self {
+        let (m, k) = optimal_params(n, p);
+        Self::with_params(m, k, HashState::with_seed(seed))
+    }
+
+    pub fn with_params(m: usize, k: usize, state: HashState) -> Self {
+        assert!(m > 0 && m <= MAX_BITS && k > 0 && k <= MAX_HASHES);
+        Self {
+            counters: allocate_heap(m),
+            m,
+            k,
+            n: 0,
+            state,
+        }
+    }
+
+    /// カウンタの数
+    pub fn len(&self) -> usize {
+        self.m
+    }
+
+    pub fn hashes(&self) -> usize {
+        self.k
+    }
+
+    /// 入っている要素の数(重複も数える)
+    pub fn size(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    pub fn add<T: Hash + ?Sized>(&mut self, x: &T) {
+        for i in indices(&self.state, self.m, self.k, x) {
+            self.counters[i] = self.counters[i].saturating_add(1);
+        }
+        self.n += 1;
+    }
+
+    pub fn contains<T: Hash + ?Sized>(&self, x: &T) -> bool {
+        indices(&self.state, self.m, self.k, x).all(|i| self.counters[i] > 0)
+    }
+
+    /// 入っていない(と確実に分かる)ときはfalse
+    /// 追加していないものを消すと他の要素の偽陰性を生むので、追加したものだけを消すこと
+    pub fn remove<T: Hash + ?Sized>(&mut self, x: &T) -> bool {
+        if !self.contains(x) {
+            return false;
+        }
+        for i in indices(&self.state, self.m, self.k, x) {
+            if self.counters[i] < u8::MAX {
+                self.counters[i] -= 1;
+            }
+        }
+        self.n -= 1;
+        true
+    }
+
+    pub fn false_positive_rate(&self) -> f64 {
+        let k = self.k as f64;
+        (1. - (-k * self.n as f64 / self.m as f64).exp()).powf(k)
+    }
+
+    pub fn is_compatible(&self, other: &Self) -> bool {
+        self.m == other.m && self.k == other.k && self.state == other.state
+    }
+
+    /// カウンタの和
+    pub fn union(&self, other: &Self) -> Option<Self> {
+        let mut c = self.combine(other, |a, b| a.saturating_add(b))?;
+        c.n = self.n + other.n;
+        Some(c)
+    }
+
+    /// カウンタの最小値
+    pub fn intersect(&self, other: &Self) -> Option<Self> {
+        let mut c = self.combine(other, std::cmp::min)?;
+        c.n = std::cmp::min(self.n, other.n);
+        Some(c)
+    }
+
+    fn combine(&self, other: &Self, f: impl Fn(u8, u8) -> u8) -> Option<Self> {
+        if !self.is_compatible(other) {
+            return None;
+        }
+        let mut c = self.clone();
+        for (a, &b) in c.counters.iter_mut().zip(other.counters.iter()) {
+            *a = f(*a, b);
+        }
+        Some(c)
+    }
+
+    /// 削除できない普通のBloomFilterにする
+    pub fn to_bloom_filter(&self) -> BloomFilter {
+        let mut f = BloomFilter::with_params(self.m, self.k, self.state);
+        for (i, &c) in self.counters.iter().enumerate() {
+            if c > 0 {
+                f.bits[i / 64] |= 1 << (i % 64);
+            }
+        }
+        f.n = self.n;
+        f
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(HEADER_LEN + self.m);
+        write_header(&mut out, self.m, self.k, self.n, &self.state);
+        out.extend_from_slice(&self.counters);
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (m, k, n, state) = read_header(bytes)?;
+        if bytes.len() != HEADER_LEN.checked_add(m)? {
+            return None;
+        }
+        let mut f = Self::with_params(m, k, state);
+        f.counters.copy_from_slice(&bytes[HEADER_LEN..]);
+        f.n = n;
+        Some(f)
+    }
+}
+
+#[cfg(test)]
+mod test_bloom {
+    use super::*;
+
+    #[test]
+    fn test_false_positive_rate() {
+        let mut f = BloomFilter::with_seed(1000, 0.01, 5);
+        for i in 0..1000 {
+            f.add(&i);
+        }
+        for i in 0..1000 {
+            assert!(f.contains(&i));
+        }
+
+        let fp = (1000..101_000).filter(|i| f.contains(i)).count();
+        let rate = fp as f64 / 100_000.;
+        assert!(rate < 0.02, "rate: {}", rate);
+        assert!((f.false_positive_rate() - 0.01).abs() < 0.005);
+
+        assert_eq!(optimal_params(usize::MAX, 1e-300).0, MAX_BITS);
+        for &p in &[0., 1., 1.5, -0.1, f64::NAN] {
+            assert!(std::panic::catch_unwind(|| optimal_params(10, p)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_union_intersect_bytes() {
+        let mut a = BloomFilter::with_seed(100, 0.01, 1);
+        let mut b = BloomFilter::with_seed(100, 0.01, 1);
+        for i in 0..50 {
+            a.add(&i);
+            b.add(&(i + 25));
+        }
+
+        let u = a.union(&b).unwrap();
+        assert!((0..75).all(|i| u.contains(&i)));
+        let x = a.intersect(&b).unwrap();
+        assert!((25..50).all(|i| x.contains(&i)));
+        assert!(BloomFilter::with_seed(100, 0.01, 2).union(&a).is_none());
+
+        let bytes = u.to_bytes();
+        let m = u.len() as u64;
+        assert_eq!(BloomFilter::from_bytes(&bytes), Some(u));
+        assert_eq!(BloomFilter::from_bytes(&bytes[..bytes.len() - 1]), None);
+
+        // 壊れた見出し: 巨大なm、k == 0、多すぎるk
+        for &(m, k) in &[(u64::MAX, 7), (1 << 62, 7), (m, 0), (m, 1000)] {
+            assert_eq!(BloomFilter::from_bytes(&with_header(&bytes, m, k)), None);
+        }
+    }
+
+    /// 見出しのmとkを書き換える
+    fn with_header(bytes: &[u8], m: u64, k: u64) -> Vec<u8> {
+        let mut b = bytes.to_vec();
+        b[..8].copy_from_slice(&m.to_le_bytes());
+        b[8..16].copy_from_slice(&k.to_le_bytes());
+        b
+    }
+
+    #[test]
+    fn test_counting() {
+        let mut f = CountingBloomFilter::with_seed(100, 0.01, 3);
+        for w in ["apple", "banana", "cherry"] {
+            f.add(w);
+        }
+        assert!(f.contains("banana"));
+        assert!(f.remove("banana"));
+        assert!(!f.contains("banana"));
+        assert!(f.contains("apple") && f.contains("cherry"));
+        assert_eq!(f.size(), 2);
+
+        let g = f.to_bloom_filter();
+        assert!(g.contains("apple"));
+
+        let mut h = CountingBloomFilter::with_seed(100, 0.01, 3);
+        h.add("banana");
+        let u = f.union(&h).unwrap();
+        assert!(u.contains("banana") && u.contains("apple"));
+        let x = f.intersect(&u).unwrap();
+        assert!(!x.contains("banana") && x.contains("cherry"));
+
+        let bytes = u.to_bytes();
+        let m = u.len() as u64;
+        assert_eq!(CountingBloomFilter::from_bytes(&bytes), Some(u));
+        for &(m, k) in &[(u64::MAX, 7), (u64::MAX - 8, 7), (m, 0), (m, 1000)] {
+            assert_eq!(
+                CountingBloomFilter::from_bytes(&with_header(&bytes, m, k)),
+                None
+            );
+        }
+    }
+}
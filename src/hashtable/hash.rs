@@ -0,0 +1,226 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash, Hasher};
+
+/// OSのエントロピーから64bitのシードを作る
+pub fn random_seed() -> u64 {
+    let mut h = RandomState::new().build_hasher();
+    h.write_u64(0);
+    h.finish()
+}
+
+/// splitmix64: シードから独立なキーを取り出すのに使う
+pub fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// ハッシュ値の上位dビットを配列のインデックスにする
+/// 本の hash(x) = (z * x.hashCode()) >>> (w - d) に相当
+pub fn hash_index(h: u64, d: u32) -> usize {
+    if d == 0 {
+        0
+    } else {
+        (h >> (64 - d)) as usize
+    }
+}
+
+/// SipHash-1-3
+/// キー(k0, k1)を知らない相手には衝突を狙って作れない
+#[derive(Debug, Clone)]
+pub struct SipHasher13 {
+    length: usize,
+    v0: u64,
+    v1: u64,
+    v2: u64,
+    v3: u64,
+    tail: u64,
+    ntail: usize,
+}
+
+macro_rules! sip_round {
+    ($v0:expr, $v1:expr, $v2:expr, $v3:expr) => {
+        $v0 = $v0.wrapping_add($v1);
+        $v1 = $v1.rotate_left(13);
+        $v1 ^= $v0;
+        $v0 = $v0.rotate_left(32);
+        $v2 = $v2.wrapping_add($v3);
+        $v3 = $v3.rotate_left(16);
+        $v3 ^= $v2;
+        $v0 = $v0.wrapping_add($v3);
+        $v3 = $v3.rotate_left(21);
+        $v3 ^= $v0;
+        $v2 = $v2.wrapping_add($v1);
+        $v1 = $v1.rotate_left(17);
+        $v1 ^= $v2;
+        $v2 = $v2.rotate_left(32);
+    };
+}
+
+fn u8to64_le(buf: &[u8], start: usize, len: usize) -> u64 {
+    let mut out = 0;
+    for i in 0..len {
+        out |= (buf[start + i] as u64) << (8 * i);
+    }
+    out
+}
+
+impl SipHasher13 {
+    pub fn new_with_keys(k0: u64, k1: u64) -> Self {
+        Self {
+            length: 0,
+            v0: k0 ^ 0x736f_6d65_7073_6575,
+            v1: k1 ^ 0x646f_7261_6e64_6f6d,
+            v2: k0 ^ 0x6c79_6765_6e65_7261,
+            v3: k1 ^ 0x7465_6462_7974_6573,
+            tail: 0,
+            ntail: 0,
+        }
+    }
+
+    fn process(&mut self, m: u64) {
+        self.v3 ^= m;
+        sip_round!(self.v0, self.v1, self.v2, self.v3);
+        self.v0 ^= m;
+    }
+}
+
+impl Hasher for SipHasher13 {
+    fn write(&mut self, msg: &[u8]) {
+        let length = msg.len();
+        self.length += length;
+
+        let mut i = 0;
+        if self.ntail != 0 {
+            // 前回の書き込みの端数を先に埋める
+            let needed = 8 - self.ntail;
+            let fill = std::cmp::min(needed, length);
+            self.tail |= u8to64_le(msg, 0, fill) << (8 * self.ntail);
+            if length < needed {
+                self.ntail += length;
+                return;
+            }
+            self.process(self.tail);
+            self.ntail = 0;
+            i = needed;
+        }
+
+        let left = (length - i) & 7;
+        while i < length - left {
+            self.process(u8to64_le(msg, i, 8));
+            i += 8;
+        }
+
+        self.tail = u8to64_le(msg, i, left);
+        self.ntail = left;
+    }
+
+    fn finish(&self) -> u64 {
+        let (mut v0, mut v1, mut v2, mut v3) = (self.v0, self.v1, self.v2, self.v3);
+        let b = (((self.length as u64) & 0xff) << 56) | self.tail;
+
+        v3 ^= b;
+        sip_round!(v0, v1, v2, v3);
+        v0 ^= b;
+
+        v2 ^= 0xff;
+        for _ in 0..3 {
+            sip_round!(v0, v1, v2, v3);
+        }
+
+        v0 ^ v1 ^ v2 ^ v3
+    }
+}
+
+/// SipHasher13を作るためのキーの組
+/// インスタンスごとにランダム、テスト用にシードから決定的にも作れる
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashState {
+    k0: u64,
+    k1: u64,
+}
+
+impl HashState {
+    pub fn new() -> Self {
+        Self::with_seed(random_seed())
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        let mut s = seed;
+        Self {
+            k0: splitmix64(&mut s),
+            k1: splitmix64(&mut s),
+        }
+    }
+
+    pub fn with_keys(k0: u64, k1: u64) -> Self {
+        Self { k0, k1 }
+    }
+
+    pub fn keys(&self) -> (u64, u64) {
+        (self.k0, self.k1)
+    }
+
+    pub fn hash<T: Hash + ?Sized>(&self, x: &T) -> u64 {
+        self.hash_one(x)
+    }
+}
+
+impl Default for HashState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BuildHasher for HashState {
+    type Hasher = SipHasher13;
+    fn build_hasher(&self) -> Self::Hasher {
+        SipHasher13::new_with_keys(self.k0, self.k1)
+    }
+}
+
+#[cfg(test)]
+mod test_hash {
+    use super::*;
+
+    #[test]
+    fn test_siphash13_matches_split_writes() {
+        let s = HashState::with_keys(0x0706050403020100, 0x0f0e0d0c0b0a0908);
+
+        let mut one = s.build_hasher();
+        one.write(b"hello, open data structures");
+
+        let mut split = s.build_hasher();
+        split.write(b"hello, ");
+        split.write(b"open");
+        split.write(b" data structures");
+
+        assert_eq!(one.finish(), split.finish());
+    }
+
+    #[test]
+    fn test_siphash13_matches_std() {
+        // std の DefaultHasher::new() はキー(0, 0)の SipHash-1-3
+        use std::collections::hash_map::DefaultHasher;
+        for len in 0..32 {
+            let msg: Vec<u8> = (0..len as u8).collect();
+            let mut expected = DefaultHasher::new();
+            expected.write(&msg);
+            let mut h = HashState::with_keys(0, 0).build_hasher();
+            h.write(&msg);
+            assert_eq!(h.finish(), expected.finish());
+        }
+    }
+
+    #[test]
+    fn test_seeded_state() {
+        let a = HashState::with_seed(42);
+        let b = HashState::with_seed(42);
+        let c = HashState::with_seed(43);
+        assert_eq!(a.hash(&"key"), b.hash(&"key"));
+        assert_ne!(a.hash(&"key"), c.hash(&"key"));
+        assert_ne!(HashState::new(), HashState::new());
+    }
+}
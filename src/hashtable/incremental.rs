@@ -0,0 +1,292 @@
+use crate::array::allocate_heap;
+use crate::hashtable::hash::{hash_index, HashState};
+use crate::hashtable::stats::HashStats;
+use std::hash::Hash;
+
+/// 1回の操作で古い配列から移すバケットの数
+/// 拡大直後(n == len / 2)から次の拡大(n == len)・縮小(4n < len)までに移行が終わる大きさ
+const MIGRATE_STEP: usize = 8;
+
+/// 配列の作り直しを一度にやらず、操作のたびに少しずつ移すチェイン法のハッシュテーブル
+///
+/// t: 本体(新しい配列)
+/// old: 移行中の古い配列
+/// moved: old[..moved]のバケットは移行済み
+/// n: 要素数
+/// d: t.len() == 2^d
+#[derive(Debug, Clone)]
+pub struct IncrementalHashTable<T> {
+    t: Box<[Vec<T>]>,
+    old: Option<Box<[Vec<T>]>>,
+    moved: usize,
+    n: usize,
+    d: u32,
+    old_d: u32,
+    state: HashState,
+    resizes: usize,
+}
+
+impl<T> IncrementalHashTable<T> {
+    /// 配列長(移行中は古い配列も含む)
+    pub fn len(&self) -> usize {
+        self.t.len() + self.old.as_ref().map_or(0, |old| old.len())
+    }
+
+    /// 内部要素の数
+    pub fn size(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    pub fn is_migrating(&self) -> bool {
+        self.old.is_some()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        let old = self
+            .old
+            .iter()
+            .flat_map(move |old| old[self.moved..].iter());
+        old.chain(self.t.iter()).flatten()
+    }
+
+    /// 要素が入りうるバケットの数(移行中は古い配列の未移行のバケットも含む)
+    fn live_buckets(&self) -> usize {
+        self.t.len() + self.old.as_ref().map_or(0, |old| old.len() - self.moved)
+    }
+
+    /// チェイン長の分布(移行中は古い配列の未移行のバケットも含む)
+    /// capacityも移行済みのバケットを数えないので、histogramの合計と一致する
+    pub fn stats(&self) -> HashStats {
+        let old = self
+            .old
+            .iter()
+            .flat_map(move |old| old[self.moved..].iter());
+        let chains = old.chain(self.t.iter()).map(|c| c.len());
+        HashStats::new(self.n, self.live_buckets(), chains, 0, self.resizes)
+    }
+}
+
+impl<T> IncrementalHashTable<T>
+where
+    T: Hash + Eq,
+{
+    pub fn new() -> Self {
+        Self::with_hash_state(HashState::new())
+    }
+
+    pub fn with_hash_state(state: HashState) -> Self {
+        Self {
+            t: allocate_heap(2),
+            old: None,
+            moved: 0,
+            n: 0,
+            d: 1,
+            old_d: 0,
+            state,
+            resizes: 0,
+        }
+    }
+
+    /// xが入っているはずのバケット
+    /// 古い配列の未移行のバケットならそちら、そうでなければ新しい配列
+    fn bucket(&self, x: &T) -> &Vec<T> {
+        let h = self.state.hash(x);
+        if let Some(old) = &self.old {
+            let i = hash_index(h, self.old_d);
+            if i >= self.moved {
+                return &old[i];
+            }
+        }
+        &self.t[hash_index(h, self.d)]
+    }
+
+    fn bucket_mut(&mut self, x: &T) -> &mut Vec<T> {
+        let h = self.state.hash(x);
+        if let Some(old) = &mut self.old {
+            let i = hash_index(h, self.old_d);
+            if i >= self.moved {
+                return &mut old[i];
+            }
+        }
+        &mut self.t[hash_index(h, self.d)]
+    }
+
+    pub fn find(&self, x: &T) -> Option<&T> {
+        self.bucket(x).iter().find(|y| *y == x)
+    }
+
+    pub fn contains(&self, x: &T) -> bool {
+        self.find(x).is_some()
+    }
+
+    pub fn add(&mut self, x: T) -> bool {
+        self.migrate();
+        if self.contains(&x) {
+            return false;
+        }
+
+        if self.n + 1 > self.t.len() {
+            self.start_resize(self.d + 1);
+        }
+
+        // 移行中は古い配列の未移行のバケットに入れることもある(findがそこを探すので)
+        self.bucket_mut(&x).push(x);
+        self.n += 1;
+        true
+    }
+
+    pub fn remove(&mut self, x: &T) -> Option<T> {
+        self.migrate();
+        let bucket = self.bucket_mut(x);
+        let j = bucket.iter().position(|y| y == x)?;
+        let removed = bucket.swap_remove(j);
+        self.n -= 1;
+
+        if 4 * self.n < self.t.len() && self.d > 1 {
+            self.start_resize(self.d - 1);
+        }
+
+        Some(removed)
+    }
+
+    /// 新しい配列を確保して、古い配列からの移行を始める
+    /// 要素の移動はmigrateで少しずつ行う
+    fn start_resize(&mut self, d: u32) {
+        // 前の移行が終わっていなければ先に終わらせる(MIGRATE_STEPが十分なら起きない)
+        while self.old.is_some() {
+            self.migrate();
+        }
+
+        let old = std::mem::replace(&mut self.t, allocate_heap(1 << d));
+        self.old = Some(old);
+        self.old_d = self.d;
+        self.d = d;
+        self.moved = 0;
+        self.resizes += 1;
+    }
+
+    /// 古い配列のバケットをMIGRATE_STEP個だけ新しい配列に移す
+    fn migrate(&mut self) {
+        let old = match self.old.as_mut() {
+            Some(old) => old,
+            None => return,
+        };
+
+        let end = std::cmp::min(self.moved + MIGRATE_STEP, old.len());
+        for i in self.moved..end {
+            for x in std::mem::take(&mut old[i]) {
+                let j = hash_index(self.state.hash(&x), self.d);
+                self.t[j].push(x);
+            }
+        }
+        self.moved = end;
+
+        if self.moved == old.len() {
+            self.old = None;
+            self.moved = 0;
+        }
+    }
+}
+
+impl<T> Default for IncrementalHashTable<T>
+where
+    T: Hash + Eq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test_incremental {
+    use super::*;
+
+    #[test]
+    fn test_add_find_remove() {
+        let mut t = IncrementalHashTable::with_hash_state(HashState::with_seed(7));
+        for i in 0..5000 {
+            assert!(t.add(i));
+            assert!(t.contains(&i));
+            assert!(t.contains(&(i / 2)));
+        }
+        assert!(!t.add(0));
+        assert_eq!(t.size(), 5000);
+        assert_eq!(t.iter().count(), 5000);
+
+        for i in 0..5000 {
+            assert_eq!(t.find(&i), Some(&i));
+        }
+
+        for i in 0..4990 {
+            assert_eq!(t.remove(&i), Some(i));
+            assert_eq!(t.remove(&i), None);
+            assert!(t.contains(&4995));
+        }
+        assert_eq!(t.size(), 10);
+        assert_eq!(t.iter().count(), 10);
+
+        let stats = t.stats();
+        assert_eq!(stats.histogram.iter().sum::<usize>(), stats.capacity);
+        assert!(stats.resizes > 2);
+    }
+
+    #[test]
+    fn test_model() {
+        use crate::rng::Rng;
+        use std::collections::HashSet;
+
+        let mut t = IncrementalHashTable::with_hash_state(HashState::with_seed(5));
+        let mut model = HashSet::new();
+        let mut rng = Rng::with_seed(1);
+        for i in 0..50_000 {
+            let x = rng.gen_range(2000);
+            if i % 3 == 2 {
+                assert_eq!(t.remove(&x), model.take(&x));
+            } else {
+                assert_eq!(t.add(x), model.insert(x));
+                assert!(t.contains(&x));
+            }
+        }
+        assert_eq!(t.size(), model.len());
+        assert!(t.iter().all(|x| model.contains(x)));
+    }
+
+    #[test]
+    fn test_bounded_migration() {
+        let mut t = IncrementalHashTable::with_hash_state(HashState::with_seed(3));
+        let mut i = 0;
+        while !t.is_migrating() {
+            t.add(i);
+            i += 1;
+        }
+
+        // 1回の操作で移るバケットはMIGRATE_STEP個まで
+        while t.is_migrating() {
+            let before = t.moved;
+            t.add(i);
+            i += 1;
+            assert!(!t.is_migrating() || t.moved - before <= MIGRATE_STEP);
+
+            // 移行の途中でもcapacityとhistogramが食い違わない
+            let stats = t.stats();
+            assert_eq!(stats.histogram.iter().sum::<usize>(), stats.capacity);
+            assert_eq!(stats.size as f64 / stats.capacity as f64, stats.load_factor);
+        }
+
+        // 移行が次の拡大より先に終わるので、拡大時に一括で移すことはない
+        let mut resizes = t.resizes;
+        for _ in 0..100_000 {
+            let migrating = t.is_migrating();
+            t.add(i);
+            i += 1;
+            if t.resizes != resizes {
+                assert!(!migrating);
+                resizes = t.resizes;
+            }
+        }
+    }
+}
@@ -1 +1,9 @@
+pub mod bloom;
 pub mod hash;
+pub mod incremental;
+pub mod perfect;
+pub mod robin_hood;
+pub mod seeded;
+pub mod set_ops;
+pub mod sketch;
+pub mod stats;
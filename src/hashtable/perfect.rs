@@ -0,0 +1,354 @@
+use crate::array::allocate_heap;
+use crate::hashtable::hash::{random_seed, splitmix64, HashState};
+use crate::hashtable::seeded::SeededHashSet;
+use crate::hashtable::stats::HashStats;
+use std::hash::Hash;
+
+/// バイト列との相互変換(PerfectHashTableの保存用)
+/// read_bytesは読んだ値と消費したバイト数を返す
+pub trait KeyBytes: Sized {
+    fn write_bytes(&self, out: &mut Vec<u8>);
+    fn read_bytes(bytes: &[u8]) -> Option<(Self, usize)>;
+}
+
+macro_rules! impl_key_bytes_int {
+    ($($t:ty),*) => {
+        $(
+            impl KeyBytes for $t {
+                fn write_bytes(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn read_bytes(bytes: &[u8]) -> Option<(Self, usize)> {
+                    const N: usize = std::mem::size_of::<$t>();
+                    let mut a = [0; N];
+                    a.copy_from_slice(bytes.get(..N)?);
+                    Some((<$t>::from_le_bytes(a), N))
+                }
+            }
+        )*
+    };
+}
+
+impl_key_bytes_int!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl KeyBytes for usize {
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        (*self as u64).write_bytes(out)
+    }
+
+    fn read_bytes(bytes: &[u8]) -> Option<(Self, usize)> {
+        u64::read_bytes(bytes).map(|(x, n)| (x as usize, n))
+    }
+}
+
+/// 長さ(u64) + UTF-8
+impl KeyBytes for String {
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        (self.len() as u64).write_bytes(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn read_bytes(bytes: &[u8]) -> Option<(Self, usize)> {
+        let (len, n) = u64::read_bytes(bytes)?;
+        let end = n.checked_add(len as usize)?;
+        let s = String::from_utf8(bytes.get(n..end)?.to_vec()).ok()?;
+        Some((s, end))
+    }
+}
+
+/// 2段目のテーブル
+/// offset: slotsの中の開始位置
+/// size: 2段目の配列長(バケットの要素数の2乗)
+/// keys: バケットの要素数(保存はせず、読み込むときにslotsから数える)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Bucket {
+    offset: usize,
+    size: usize,
+    keys: usize,
+    state: HashState,
+}
+
+/// FKSの2段階の完全ハッシュ
+/// 1段目でn個のバケットに分け、要素数bのバケットは大きさb^2の2段目のテーブルに衝突なしで入れる
+/// 全体の大きさはO(n)、findは最悪でもハッシュ2回
+///
+/// state: 1段目のハッシュ
+/// buckets: 1段目(n個)
+/// slots: 2段目のテーブルをつなげたもの
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PerfectHashTable<T> {
+    state: HashState,
+    buckets: Box<[Bucket]>,
+    slots: Box<[Option<T>]>,
+    n: usize,
+}
+
+impl<T> PerfectHashTable<T> {
+    /// 2段目のテーブルの大きさの合計
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// 内部要素の数
+    pub fn size(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.slots.iter().flatten()
+    }
+
+    /// 1段目のバケットの要素数の分布
+    /// 探索は常にハッシュ2回なので、ここでは2段目のテーブルの偏りを見る
+    /// capacityは1段目のバケット数なので、histogramの合計と一致する
+    pub fn stats(&self) -> HashStats {
+        let sizes = self.buckets.iter().map(|b| b.keys);
+        HashStats::new(self.n, self.buckets.len(), sizes, 0, 0)
+    }
+}
+
+impl<T> PerfectHashTable<T>
+where
+    T: Hash + Eq + Clone,
+{
+    pub fn build(keys: &[T]) -> Self {
+        Self::build_with_seed(keys, random_seed())
+    }
+
+    /// 重複したキーは1つにまとめる
+    pub fn build_with_seed(keys: &[T], mut seed: u64) -> Self {
+        let mut keys = keys.to_vec();
+        let mut seen = SeededHashSet::with_seed(seed);
+        keys.retain(|k| seen.add(k.clone()));
+        let n = keys.len();
+        let m = std::cmp::max(n, 1);
+
+        // 1段目: バケットの大きさの2乗和が4n以下になるまでハッシュを選び直す
+        let (state, groups) = loop {
+            let state = HashState::with_seed(splitmix64(&mut seed));
+            let mut groups: Vec<Vec<T>> = (0..m).map(|_| vec![]).collect();
+            for k in keys.iter() {
+                groups[(state.hash(k) % m as u64) as usize].push(k.clone());
+            }
+            let squares: usize = groups.iter().map(|g| g.len() * g.len()).sum();
+            if squares <= 4 * m {
+                break (state, groups);
+            }
+        };
+
+        // 2段目: 要素数bのバケットごとに、大きさb^2で衝突しないハッシュを選び直す
+        let total = groups.iter().map(|g| g.len() * g.len()).sum();
+        let mut slots: Box<[Option<T>]> = allocate_heap(total);
+        let mut buckets = Vec::with_capacity(m);
+        let mut offset = 0;
+        for g in groups {
+            let keys = g.len();
+            let size = keys * keys;
+            let state = 'retry: loop {
+                let state = HashState::with_seed(splitmix64(&mut seed));
+                let mut used = vec![false; size];
+                for k in g.iter() {
+                    let j = (state.hash(k) % size as u64) as usize;
+                    if used[j] {
+                        continue 'retry;
+                    }
+                    used[j] = true;
+                }
+                break state;
+            };
+
+            for k in g {
+                let j = (state.hash(&k) % size as u64) as usize;
+                slots[offset + j] = Some(k);
+            }
+            buckets.push(Bucket {
+                offset,
+                size,
+                keys,
+                state,
+            });
+            offset += size;
+        }
+
+        Self {
+            state,
+            buckets: buckets.into_boxed_slice(),
+            slots,
+            n,
+        }
+    }
+
+    fn slot(&self, x: &T) -> Option<usize> {
+        let i = (self.state.hash(x) % self.buckets.len() as u64) as usize;
+        let b = &self.buckets[i];
+        if b.size == 0 {
+            return None;
+        }
+        Some(b.offset + (b.state.hash(x) % b.size as u64) as usize)
+    }
+
+    pub fn find(&self, x: &T) -> Option<&T> {
+        self.slots[self.slot(x)?].as_ref().filter(|y| *y == x)
+    }
+
+    pub fn contains(&self, x: &T) -> bool {
+        self.find(x).is_some()
+    }
+}
+
+fn write_state(out: &mut Vec<u8>, state: &HashState) {
+    let (k0, k1) = state.keys();
+    k0.write_bytes(out);
+    k1.write_bytes(out);
+}
+
+fn read_state(bytes: &[u8], at: &mut usize) -> Option<HashState> {
+    let k0 = read_u64(bytes, at)?;
+    let k1 = read_u64(bytes, at)?;
+    Some(HashState::with_keys(k0, k1))
+}
+
+fn read_u64(bytes: &[u8], at: &mut usize) -> Option<u64> {
+    let (x, n) = u64::read_bytes(bytes.get(*at..)?)?;
+    *at += n;
+    Some(x)
+}
+
+/// 保存形式
+/// n, 1段目のキー, バケット数, (offset, size, キー) * バケット数,
+/// slotsの長さ, (0 | 1 + 要素) * slotsの長さ
+impl<T> PerfectHashTable<T>
+where
+    T: KeyBytes,
+{
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![];
+        (self.n as u64).write_bytes(&mut out);
+        write_state(&mut out, &self.state);
+
+        (self.buckets.len() as u64).write_bytes(&mut out);
+        for b in self.buckets.iter() {
+            (b.offset as u64).write_bytes(&mut out);
+            (b.size as u64).write_bytes(&mut out);
+            write_state(&mut out, &b.state);
+        }
+
+        (self.slots.len() as u64).write_bytes(&mut out);
+        for s in self.slots.iter() {
+            match s {
+                None => out.push(0),
+                Some(x) => {
+                    out.push(1);
+                    x.write_bytes(&mut out);
+                }
+            }
+        }
+        out
+    }
+
+    /// 作り直さずにそのまま読み込む
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut at = 0;
+        let n = read_u64(bytes, &mut at)? as usize;
+        let state = read_state(bytes, &mut at)?;
+
+        let m = read_u64(bytes, &mut at)? as usize;
+        let mut buckets = Vec::with_capacity(std::cmp::min(m, bytes.len()));
+        for _ in 0..m {
+            let offset = read_u64(bytes, &mut at)? as usize;
+            let size = read_u64(bytes, &mut at)? as usize;
+            let state = read_state(bytes, &mut at)?;
+            buckets.push(Bucket {
+                offset,
+                size,
+                keys: 0,
+                state,
+            });
+        }
+
+        let len = read_u64(bytes, &mut at)? as usize;
+        let mut slots = Vec::with_capacity(std::cmp::min(len, bytes.len()));
+        for _ in 0..len {
+            let tag = *bytes.get(at)?;
+            at += 1;
+            if tag == 0 {
+                slots.push(None);
+            } else {
+                let (x, k) = T::read_bytes(bytes.get(at..)?)?;
+                at += k;
+                slots.push(Some(x));
+            }
+        }
+
+        let in_range = buckets
+            .iter()
+            .all(|b| b.offset.checked_add(b.size).is_some_and(|end| end <= len));
+        if m == 0 || at != bytes.len() || !in_range {
+            return None;
+        }
+        for b in buckets.iter_mut() {
+            b.keys = slots[b.offset..b.offset + b.size].iter().flatten().count();
+        }
+
+        Some(Self {
+            state,
+            buckets: buckets.into_boxed_slice(),
+            slots: slots.into_boxed_slice(),
+            n,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_perfect {
+    use super::*;
+
+    #[test]
+    fn test_build_find() {
+        let keys: Vec<u64> = (0..10_000).map(|i| i * 7919).collect();
+        let t = PerfectHashTable::build_with_seed(&keys, 1);
+        assert_eq!(t.size(), 10_000);
+        assert!(t.len() <= 4 * 10_000);
+        for k in keys.iter() {
+            assert_eq!(t.find(k), Some(k));
+        }
+        assert!(!t.contains(&1));
+        assert_eq!(t.iter().count(), 10_000);
+
+        let stats = t.stats();
+        assert_eq!(stats.capacity, 10_000);
+        assert_eq!(stats.histogram.iter().sum::<usize>(), stats.capacity);
+        let keys: usize = stats.histogram.iter().enumerate().map(|(k, c)| k * c).sum();
+        assert_eq!(keys, 10_000);
+    }
+
+    #[test]
+    fn test_bytes() {
+        let words: Vec<String> = "alpha beta gamma delta alpha epsilon"
+            .split(' ')
+            .map(|s| s.to_string())
+            .collect();
+        let t = PerfectHashTable::build(&words);
+        assert_eq!(t.size(), 5);
+
+        let bytes = t.to_bytes();
+        let u = PerfectHashTable::<String>::from_bytes(&bytes).unwrap();
+        assert_eq!(t, u);
+        assert!(u.contains(&"gamma".to_string()));
+        assert!(!u.contains(&"zeta".to_string()));
+
+        assert!(PerfectHashTable::<String>::from_bytes(&bytes[..bytes.len() - 1]).is_none());
+        let empty = PerfectHashTable::<u32>::build(&[]);
+        assert!(!empty.contains(&0));
+        assert_eq!(
+            (empty.stats().capacity, empty.stats().histogram),
+            (1, vec![1])
+        );
+        assert_eq!(PerfectHashTable::from_bytes(&empty.to_bytes()), Some(empty));
+    }
+}
@@ -0,0 +1,265 @@
+use crate::array::allocate_heap;
+use crate::hashtable::hash::{hash_index, HashState};
+use crate::hashtable::stats::HashStats;
+use std::hash::Hash;
+
+/// x: 要素
+/// dist: 本来の位置(ハッシュ値)からの距離
+#[derive(Debug, Clone)]
+struct Bucket<T> {
+    x: T,
+    dist: usize,
+}
+
+/// t: 本体
+/// n: 要素数
+/// d: t.len() == 2^d
+/// resizes: resizeした回数
+#[derive(Debug, Clone)]
+pub struct RobinHoodHashTable<T> {
+    t: Box<[Option<Bucket<T>>]>,
+    n: usize,
+    d: u32,
+    state: HashState,
+    resizes: usize,
+}
+
+impl<T> RobinHoodHashTable<T> {
+    /// 配列長
+    pub fn len(&self) -> usize {
+        self.t.len()
+    }
+
+    /// 内部要素の数
+    pub fn size(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.t.iter().flatten().map(|b| &b.x)
+    }
+
+    /// 探索距離の分布
+    /// backward-shift deletionなのでtombstoneは常に0
+    pub fn stats(&self) -> HashStats {
+        let probes = self.t.iter().flatten().map(|b| b.dist);
+        HashStats::new(self.n, self.len(), probes, 0, self.resizes)
+    }
+
+    fn next_index(&self, i: usize) -> usize {
+        (i + 1) & (self.len() - 1)
+    }
+}
+
+impl<T> RobinHoodHashTable<T>
+where
+    T: Hash + Eq,
+{
+    pub fn new() -> Self {
+        Self::with_hash_state(HashState::new())
+    }
+
+    pub fn with_hash_state(state: HashState) -> Self {
+        Self {
+            t: allocate_heap(2),
+            n: 0,
+            d: 1,
+            state,
+            resizes: 0,
+        }
+    }
+
+    fn hash(&self, x: &T) -> usize {
+        hash_index(self.state.hash(x), self.d)
+    }
+
+    /// xが入っている位置
+    fn find_index(&self, x: &T) -> Option<usize> {
+        let mut i = self.hash(x);
+        let mut dist = 0;
+
+        while let Some(b) = &self.t[i] {
+            // xがここまでにあるなら、xより遠くから来た要素に場所を譲っていないはず
+            if b.dist < dist {
+                return None;
+            }
+            if b.x == *x {
+                return Some(i);
+            }
+            i = self.next_index(i);
+            dist += 1;
+        }
+
+        None
+    }
+
+    pub fn find(&self, x: &T) -> Option<&T> {
+        self.find_index(x)
+            .and_then(|i| self.t[i].as_ref().map(|b| &b.x))
+    }
+
+    pub fn contains(&self, x: &T) -> bool {
+        self.find_index(x).is_some()
+    }
+
+    pub fn add(&mut self, x: T) -> bool {
+        if self.find_index(&x).is_some() {
+            return false;
+        }
+
+        if 2 * (self.n + 1) > self.len() {
+            self.resize();
+        }
+
+        self.insert(x);
+        self.n += 1;
+        true
+    }
+
+    pub fn remove(&mut self, x: &T) -> Option<T> {
+        let mut i = self.find_index(x)?;
+        let removed = self.t[i].take().map(|b| b.x);
+        self.n -= 1;
+
+        // tombstoneを置かずに後ろの要素を1つずつ前に詰める
+        let mut j = self.next_index(i);
+        while let Some(b) = self.t[j].as_mut() {
+            if b.dist == 0 {
+                break;
+            }
+            b.dist -= 1;
+            self.t[i] = self.t[j].take();
+            i = j;
+            j = self.next_index(j);
+        }
+
+        if 8 * self.n < self.len() && self.d > 1 {
+            self.resize();
+        }
+
+        removed
+    }
+
+    /// 重複を確認せずに入れる
+    /// 自分より本来の位置に近い要素に出会ったら場所を奪い、そちらを先に進める
+    fn insert(&mut self, x: T) {
+        let mut i = self.hash(&x);
+        let mut cur = Bucket { x, dist: 0 };
+
+        loop {
+            match self.t[i].as_mut() {
+                None => {
+                    self.t[i] = Some(cur);
+                    return;
+                }
+                Some(b) => {
+                    if b.dist < cur.dist {
+                        std::mem::swap(b, &mut cur);
+                    }
+                }
+            }
+            i = self.next_index(i);
+            cur.dist += 1;
+        }
+    }
+
+    /// 2^d >= 3nとなる最小のdで作り直す
+    pub fn resize(&mut self) {
+        let mut d = 1;
+        while (1 << d) < 3 * self.n {
+            d += 1;
+        }
+
+        let old_t = std::mem::replace(&mut self.t, allocate_heap(1 << d));
+        self.d = d;
+        self.resizes += 1;
+        for b in Vec::from(old_t).into_iter().flatten() {
+            self.insert(b.x);
+        }
+    }
+}
+
+impl<T> Default for RobinHoodHashTable<T>
+where
+    T: Hash + Eq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test_robin_hood {
+    use super::*;
+
+    /// 各要素のdistが本来の位置からの実際の距離と一致し、
+    /// 途中に空きがないことを確かめる
+    fn check_invariants<T: Hash + Eq>(t: &RobinHoodHashTable<T>) {
+        for i in 0..t.len() {
+            if let Some(b) = &t.t[i] {
+                let home = t.hash(&b.x);
+                assert_eq!((home + b.dist) & (t.len() - 1), i);
+                for k in 0..b.dist {
+                    assert!(t.t[(home + k) & (t.len() - 1)].is_some());
+                }
+            }
+        }
+        assert_eq!(t.iter().count(), t.size());
+    }
+
+    #[test]
+    fn test_add_find_remove() {
+        let mut t = RobinHoodHashTable::with_hash_state(HashState::with_seed(1));
+        for i in 0..1000 {
+            assert!(t.add(i));
+        }
+        assert!(!t.add(10));
+        assert_eq!(t.size(), 1000);
+        check_invariants(&t);
+
+        let stats = t.stats();
+        assert_eq!(stats.size, 1000);
+        assert_eq!(stats.histogram.iter().sum::<usize>(), 1000);
+        assert!(stats.load_factor <= 0.5);
+        assert!(stats.resizes > 0);
+        assert_eq!(stats.tombstones, 0);
+
+        for i in 0..1000 {
+            assert_eq!(t.find(&i), Some(&i));
+        }
+        assert_eq!(t.find(&1000), None);
+
+        for i in (0..1000).step_by(2) {
+            assert_eq!(t.remove(&i), Some(i));
+        }
+        assert_eq!(t.remove(&0), None);
+        check_invariants(&t);
+
+        for i in 0..1000 {
+            assert_eq!(t.contains(&i), i % 2 == 1);
+        }
+
+        for i in (1..1000).step_by(2) {
+            t.remove(&i);
+        }
+        assert!(t.is_empty());
+        assert_eq!(t.len(), 2);
+    }
+
+    #[test]
+    fn test_strings() {
+        let mut t = RobinHoodHashTable::new();
+        for w in "the quick brown fox jumps over the lazy dog".split(' ') {
+            t.add(w.to_string());
+        }
+        assert_eq!(t.size(), 8);
+        assert!(t.contains(&"fox".to_string()));
+        assert_eq!(t.remove(&"the".to_string()), Some("the".to_string()));
+        assert!(!t.contains(&"the".to_string()));
+        check_invariants(&t);
+    }
+}
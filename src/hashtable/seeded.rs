@@ -0,0 +1,439 @@
+use crate::array::allocate_heap;
+use crate::hashtable::hash::{hash_index, random_seed, splitmix64, HashState};
+use crate::hashtable::stats::HashStats;
+use std::hash::Hash;
+use std::iter::FromIterator;
+
+/// チェインがこれより長くなったら攻撃を疑う
+const MIN_CHAIN_LIMIT: usize = 8;
+
+/// インスタンスごとにランダムなキーのSipHashを使うチェイン法のハッシュマップ
+/// 異常に長いチェインができたらキーを取り替えて作り直す
+///
+/// t: 本体
+/// n: 要素数
+/// d: t.len() == 2^d
+/// seed: 次のキーを作るためのsplitmix64の状態
+/// chain_limit: これより長いチェインができたら作り直す
+#[derive(Debug, Clone)]
+pub struct SeededHashMap<K, V> {
+    t: Box<[Vec<(K, V)>]>,
+    n: usize,
+    d: u32,
+    state: HashState,
+    seed: u64,
+    chain_limit: usize,
+    resizes: usize,
+    reseeds: usize,
+}
+
+impl<K, V> SeededHashMap<K, V> {
+    /// 配列長
+    pub fn len(&self) -> usize {
+        self.t.len()
+    }
+
+    /// 内部要素の数
+    pub fn size(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// キーを取り替えて作り直した回数
+    pub fn reseeds(&self) -> usize {
+        self.reseeds
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            buckets: self.t.iter(),
+            chain: [].iter(),
+        }
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys { iter: self.iter() }
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, v)| v)
+    }
+
+    /// チェイン長の分布
+    pub fn stats(&self) -> HashStats {
+        let chains = self.t.iter().map(|c| c.len());
+        HashStats::new(self.n, self.len(), chains, 0, self.resizes)
+    }
+}
+
+impl<K, V> SeededHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    pub fn new() -> Self {
+        Self::with_seed(random_seed())
+    }
+
+    /// 同じシードなら同じキーの列を使う(テスト用)
+    pub fn with_seed(mut seed: u64) -> Self {
+        let state = HashState::with_seed(splitmix64(&mut seed));
+        Self {
+            t: allocate_heap(1),
+            n: 0,
+            d: 0,
+            state,
+            seed,
+            chain_limit: MIN_CHAIN_LIMIT,
+            resizes: 0,
+            reseeds: 0,
+        }
+    }
+
+    fn hash(&self, k: &K) -> usize {
+        hash_index(self.state.hash(k), self.d)
+    }
+
+    pub fn get_key_value(&self, k: &K) -> Option<(&K, &V)> {
+        self.t[self.hash(k)]
+            .iter()
+            .find(|(y, _)| y == k)
+            .map(|(k, v)| (k, v))
+    }
+
+    pub fn get(&self, k: &K) -> Option<&V> {
+        self.get_key_value(k).map(|(_, v)| v)
+    }
+
+    pub fn get_mut(&mut self, k: &K) -> Option<&mut V> {
+        let i = self.hash(k);
+        self.t[i].iter_mut().find(|(y, _)| y == k).map(|(_, v)| v)
+    }
+
+    pub fn contains_key(&self, k: &K) -> bool {
+        self.get_key_value(k).is_some()
+    }
+
+    /// すでにkがあれば値を置き換えて古い値を返す
+    pub fn insert(&mut self, k: K, v: V) -> Option<V> {
+        if let Some(old) = self.get_mut(&k) {
+            return Some(std::mem::replace(old, v));
+        }
+
+        if self.n + 1 > self.len() {
+            self.resize();
+        }
+
+        let i = self.hash(&k);
+        self.t[i].push((k, v));
+        self.n += 1;
+
+        if self.t[i].len() > self.chain_limit {
+            self.reseed();
+        }
+
+        None
+    }
+
+    pub fn remove_entry(&mut self, k: &K) -> Option<(K, V)> {
+        let i = self.hash(k);
+        let j = self.t[i].iter().position(|(y, _)| y == k)?;
+        let entry = self.t[i].swap_remove(j);
+        self.n -= 1;
+
+        if 3 * self.n < self.len() && self.d > 0 {
+            self.resize();
+        }
+
+        Some(entry)
+    }
+
+    pub fn remove(&mut self, k: &K) -> Option<V> {
+        self.remove_entry(k).map(|(_, v)| v)
+    }
+
+    /// fがfalseを返した要素を消す
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        for chain in self.t.iter_mut() {
+            chain.retain_mut(|(k, v)| f(k, v));
+        }
+        self.n = self.t.iter().map(|c| c.len()).sum();
+
+        if 3 * self.n < self.len() && self.d > 0 {
+            self.resize();
+        }
+    }
+
+    /// 2^d > nとなる最小のdで作り直す
+    pub fn resize(&mut self) {
+        let mut d = 0;
+        while (1 << d) < self.n + 1 {
+            d += 1;
+        }
+        self.d = d;
+        // reseedで緩めた上限は戻さない
+        self.chain_limit = std::cmp::max(self.chain_limit, 2 * d as usize);
+        self.resizes += 1;
+        self.rebuild();
+    }
+
+    /// 新しいキーで作り直す
+    /// それでも長いチェインが残るならハッシュ関数ではなくHashの実装が偏っているので、
+    /// 作り直しを繰り返さないように上限を緩める
+    fn reseed(&mut self) {
+        self.state = HashState::with_seed(splitmix64(&mut self.seed));
+        self.reseeds += 1;
+        self.rebuild();
+
+        let longest = self.t.iter().map(|c| c.len()).max().unwrap_or(0);
+        if longest > self.chain_limit {
+            self.chain_limit = 2 * longest;
+        }
+    }
+
+    fn rebuild(&mut self) {
+        let old_t = std::mem::replace(&mut self.t, allocate_heap(1 << self.d));
+        for (k, v) in Vec::from(old_t).into_iter().flatten() {
+            let i = self.hash(&k);
+            self.t[i].push((k, v));
+        }
+    }
+}
+
+impl<K, V> Default for SeededHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// バケットを順に見ていくイテレータ
+#[derive(Debug, Clone)]
+pub struct Iter<'a, K, V> {
+    buckets: std::slice::Iter<'a, Vec<(K, V)>>,
+    chain: std::slice::Iter<'a, (K, V)>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((k, v)) = self.chain.next() {
+                return Some((k, v));
+            }
+            self.chain = self.buckets.next()?.iter();
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Keys<'a, K, V> {
+    iter: Iter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for Keys<'a, K, V> {
+    type Item = &'a K;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(k, _)| k)
+    }
+}
+
+/// SeededHashMapのキーだけを使う集合
+#[derive(Debug, Clone)]
+pub struct SeededHashSet<T> {
+    map: SeededHashMap<T, ()>,
+}
+
+impl<T> SeededHashSet<T> {
+    /// 配列長
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// 内部要素の数
+    pub fn size(&self) -> usize {
+        self.map.size()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn reseeds(&self) -> usize {
+        self.map.reseeds()
+    }
+
+    pub fn iter(&self) -> Keys<'_, T, ()> {
+        self.map.keys()
+    }
+
+    pub fn stats(&self) -> HashStats {
+        self.map.stats()
+    }
+}
+
+impl<T> SeededHashSet<T>
+where
+    T: Hash + Eq,
+{
+    pub fn new() -> Self {
+        Self {
+            map: SeededHashMap::new(),
+        }
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            map: SeededHashMap::with_seed(seed),
+        }
+    }
+
+    pub fn add(&mut self, x: T) -> bool {
+        if self.map.contains_key(&x) {
+            return false;
+        }
+        self.map.insert(x, ());
+        true
+    }
+
+    pub fn remove(&mut self, x: &T) -> Option<T> {
+        self.map.remove_entry(x).map(|(x, _)| x)
+    }
+
+    pub fn find(&self, x: &T) -> Option<&T> {
+        self.map.get_key_value(x).map(|(x, _)| x)
+    }
+
+    pub fn contains(&self, x: &T) -> bool {
+        self.map.contains_key(x)
+    }
+
+    /// fがfalseを返した要素を消す
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.map.retain(|x, _| f(x));
+    }
+}
+
+impl<T> Default for SeededHashSet<T>
+where
+    T: Hash + Eq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> FromIterator<T> for SeededHashSet<T>
+where
+    T: Hash + Eq,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut s = Self::new();
+        s.extend(iter);
+        s
+    }
+}
+
+impl<T> Extend<T> for SeededHashSet<T>
+where
+    T: Hash + Eq,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(x);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_seeded {
+    use super::*;
+
+    #[test]
+    fn test_map() {
+        let mut m = SeededHashMap::with_seed(0);
+        for i in 0..1000 {
+            assert_eq!(m.insert(i, i * 10), None);
+        }
+        assert_eq!(m.insert(5, 0), Some(50));
+        assert_eq!(m.get(&5), Some(&0));
+        *m.get_mut(&6).unwrap() += 1;
+        assert_eq!(m.get(&6), Some(&61));
+        assert_eq!(m.size(), 1000);
+
+        for i in 0..990 {
+            assert!(m.remove(&i).is_some());
+        }
+        assert_eq!(m.remove(&0), None);
+        assert_eq!(m.size(), 10);
+        assert_eq!(m.values().sum::<usize>(), (990..1000).map(|i| i * 10).sum());
+        assert_eq!(m.reseeds(), 0);
+    }
+
+    #[test]
+    fn test_deterministic_seed() {
+        let mut a = SeededHashSet::with_seed(99);
+        let mut b = SeededHashSet::with_seed(99);
+        for i in 0..100 {
+            a.add(i);
+            b.add(i);
+        }
+        assert!(a.iter().eq(b.iter()));
+        assert_eq!(a.find(&3), Some(&3));
+        assert_eq!(a.remove(&3), Some(3));
+        assert!(!a.contains(&3));
+    }
+
+    #[test]
+    fn test_reseed_on_attack() {
+        let mut m = SeededHashMap::with_seed(1);
+
+        // 最初のキーを知っている攻撃者が、上位10ビットが0になるキーを集める
+        let state = m.state;
+        let keys: Vec<u64> = (0..)
+            .filter(|k| state.hash(k) >> 54 == 0)
+            .take(64)
+            .collect();
+
+        for &k in &keys {
+            m.insert(k, ());
+        }
+
+        assert!(m.reseeds() > 0);
+        assert_ne!(m.state, state);
+        assert!(m.stats().max_probe <= m.chain_limit);
+        for k in &keys {
+            assert!(m.contains_key(k));
+        }
+    }
+
+    #[test]
+    fn test_degenerate_hash() {
+        // Hashの実装自体が全部同じ値を返すときは作り直しを繰り返さない
+        #[derive(PartialEq, Eq)]
+        struct Bad(u32);
+        impl Hash for Bad {
+            fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                0.hash(state)
+            }
+        }
+
+        let mut m = SeededHashMap::with_seed(2);
+        for i in 0..200 {
+            m.insert(Bad(i), i);
+        }
+        assert_eq!(m.size(), 200);
+        assert!(m.reseeds() <= 5);
+        assert_eq!(m.get(&Bad(150)), Some(&150));
+    }
+}
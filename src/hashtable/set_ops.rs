@@ -0,0 +1,222 @@
+use crate::hashtable::seeded::{Keys, SeededHashSet};
+use std::hash::Hash;
+use std::iter::Chain;
+
+type SetIter<'a, T> = Keys<'a, T, ()>;
+
+/// selfにあってotherにない要素
+#[derive(Debug, Clone)]
+pub struct Difference<'a, T> {
+    iter: SetIter<'a, T>,
+    other: &'a SeededHashSet<T>,
+}
+
+impl<'a, T: Hash + Eq> Iterator for Difference<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        let other = self.other;
+        self.iter.find(|x| !other.contains(x))
+    }
+}
+
+/// 両方にある要素
+#[derive(Debug, Clone)]
+pub struct Intersection<'a, T> {
+    iter: SetIter<'a, T>,
+    other: &'a SeededHashSet<T>,
+}
+
+impl<'a, T: Hash + Eq> Iterator for Intersection<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        let other = self.other;
+        self.iter.find(|x| other.contains(x))
+    }
+}
+
+/// selfの要素と、otherにあってselfにない要素
+#[derive(Debug, Clone)]
+pub struct Union<'a, T> {
+    iter: Chain<SetIter<'a, T>, Difference<'a, T>>,
+}
+
+impl<'a, T: Hash + Eq> Iterator for Union<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+}
+
+/// どちらか一方にだけある要素
+#[derive(Debug, Clone)]
+pub struct SymmetricDifference<'a, T> {
+    iter: Chain<Difference<'a, T>, Difference<'a, T>>,
+}
+
+impl<'a, T: Hash + Eq> Iterator for SymmetricDifference<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+}
+
+impl<T> SeededHashSet<T>
+where
+    T: Hash + Eq,
+{
+    pub fn difference<'a>(&'a self, other: &'a Self) -> Difference<'a, T> {
+        Difference {
+            iter: self.iter(),
+            other,
+        }
+    }
+
+    /// 小さい方を走査する
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> Intersection<'a, T> {
+        let (small, large) = if self.size() <= other.size() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        Intersection {
+            iter: small.iter(),
+            other: large,
+        }
+    }
+
+    pub fn union<'a>(&'a self, other: &'a Self) -> Union<'a, T> {
+        Union {
+            iter: self.iter().chain(other.difference(self)),
+        }
+    }
+
+    pub fn symmetric_difference<'a>(&'a self, other: &'a Self) -> SymmetricDifference<'a, T> {
+        SymmetricDifference {
+            iter: self.difference(other).chain(other.difference(self)),
+        }
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.size() <= other.size() && self.iter().all(|x| other.contains(x))
+    }
+
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.intersection(other).next().is_none()
+    }
+
+    /// self = self ∪ other
+    pub fn union_with(&mut self, other: &Self)
+    where
+        T: Clone,
+    {
+        for x in other.iter() {
+            if !self.contains(x) {
+                self.add(x.clone());
+            }
+        }
+    }
+
+    /// self = self ∩ other
+    pub fn intersect_with(&mut self, other: &Self) {
+        self.retain(|x| other.contains(x));
+    }
+
+    /// self = self \ other
+    pub fn difference_with(&mut self, other: &Self) {
+        if self.size() <= other.size() {
+            self.retain(|x| !other.contains(x));
+        } else {
+            for x in other.iter() {
+                self.remove(x);
+            }
+        }
+    }
+
+    /// self = self △ other
+    pub fn symmetric_difference_with(&mut self, other: &Self)
+    where
+        T: Clone,
+    {
+        for x in other.iter() {
+            if self.remove(x).is_none() {
+                self.add(x.clone());
+            }
+        }
+    }
+}
+
+impl<T> PartialEq for SeededHashSet<T>
+where
+    T: Hash + Eq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.size() == other.size() && self.is_subset(other)
+    }
+}
+
+impl<T> Eq for SeededHashSet<T> where T: Hash + Eq {}
+
+#[cfg(test)]
+mod test_set_ops {
+    use super::*;
+
+    fn set(xs: &[i32]) -> SeededHashSet<i32> {
+        xs.iter().cloned().collect()
+    }
+
+    fn sorted<'a>(it: impl Iterator<Item = &'a i32>) -> Vec<i32> {
+        let mut v: Vec<i32> = it.cloned().collect();
+        v.sort();
+        v
+    }
+
+    #[test]
+    fn test_lazy() {
+        let a = set(&[1, 2, 3, 4]);
+        let b = set(&[3, 4, 5]);
+
+        assert_eq!(sorted(a.union(&b)), vec![1, 2, 3, 4, 5]);
+        assert_eq!(sorted(a.intersection(&b)), vec![3, 4]);
+        assert_eq!(sorted(a.difference(&b)), vec![1, 2]);
+        assert_eq!(sorted(b.difference(&a)), vec![5]);
+        assert_eq!(sorted(a.symmetric_difference(&b)), vec![1, 2, 5]);
+
+        assert!(set(&[3, 4]).is_subset(&a));
+        assert!(a.is_superset(&set(&[1])));
+        assert!(!b.is_subset(&a));
+        assert!(a.is_disjoint(&set(&[7, 8])));
+        assert!(!a.is_disjoint(&b));
+        assert_eq!(a, set(&[4, 3, 2, 1]));
+    }
+
+    #[test]
+    fn test_in_place() {
+        let b = set(&[3, 4, 5]);
+
+        let mut a = set(&[1, 2, 3, 4]);
+        a.union_with(&b);
+        assert_eq!(a, set(&[1, 2, 3, 4, 5]));
+
+        let mut a = set(&[1, 2, 3, 4]);
+        a.intersect_with(&b);
+        assert_eq!(a, set(&[3, 4]));
+
+        let mut a = set(&[1, 2, 3, 4]);
+        a.difference_with(&b);
+        assert_eq!(a, set(&[1, 2]));
+
+        let mut a = set(&[1, 2, 3, 4]);
+        a.symmetric_difference_with(&b);
+        assert_eq!(a, set(&[1, 2, 5]));
+
+        let mut a: SeededHashSet<i32> = (0..1000).collect();
+        a.retain(|x| x % 3 == 0);
+        assert_eq!(a.size(), 334);
+        assert!(a.len() < 1024);
+        assert!(a.iter().all(|x| x % 3 == 0));
+    }
+}
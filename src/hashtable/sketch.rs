@@ -0,0 +1,300 @@
+use crate::array::allocate_heap;
+use crate::hashtable::hash::{splitmix64, HashState};
+use std::hash::Hash;
+
+/// Count-Min sketch
+///
+/// 幅w = ceil(e / ε)、深さd = ceil(ln(1 / δ))のとき、
+/// 総数Nに対して 真の頻度 <= estimate(x) <= 真の頻度 + εN が確率1 - δ以上で成り立つ
+///
+/// t: d行w列のカウンタ(行ごとに並べる)
+/// n: 足した総数N
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CountMinSketch {
+    t: Box<[u64]>,
+    w: usize,
+    d: usize,
+    n: u64,
+    state: HashState,
+}
+
+impl CountMinSketch {
+    pub fn new(epsilon: f64, delta: f64) -> Self {
+        let (w, d) = Self::params(epsilon, delta);
+        Self::with_params(w, d, HashState::new())
+    }
+
+    pub fn with_seed(epsilon: f64, delta: f64, seed: u64) -> Self {
+        let (w, d) = Self::params(epsilon, delta);
+        Self::with_params(w, d, HashState::with_seed(seed))
+    }
+
+    pub fn with_params(w: usize, d: usize, state: HashState) -> Self {
+        assert!(w > 0 && d > 0);
+        let cells = w.checked_mul(d).expect("sketch too large");
+        Self {
+            t: allocate_heap(cells),
+            w,
+            d,
+            n: 0,
+            state,
+        }
+    }
+
+    /// 誤差εと失敗確率δから(w, d)を決める
+    /// εもδも0より大きく1より小さいこと
+    pub fn params(epsilon: f64, delta: f64) -> (usize, usize) {
+        assert!(
+            epsilon > 0. && epsilon < 1.,
+            "epsilon must be in (0, 1), got {}",
+            epsilon
+        );
+        assert!(
+            delta > 0. && delta < 1.,
+            "delta must be in (0, 1), got {}",
+            delta
+        );
+        let w = (std::f64::consts::E / epsilon).ceil() as usize;
+        let d = (1. / delta).ln().ceil() as usize;
+        (std::cmp::max(w, 1), std::cmp::max(d, 1))
+    }
+
+    pub fn width(&self) -> usize {
+        self.w
+    }
+
+    pub fn depth(&self) -> usize {
+        self.d
+    }
+
+    /// 足した総数N
+    pub fn total(&self) -> u64 {
+        self.n
+    }
+
+    /// 過大評価の上限εN (確率1 - δで成り立つ)
+    pub fn error_bound(&self) -> f64 {
+        std::f64::consts::E / self.w as f64 * self.n as f64
+    }
+
+    /// 行rで使う列 h1 + r * h2 mod w
+    fn cells<T: Hash + ?Sized>(&self, x: &T) -> impl Iterator<Item = usize> {
+        let h1 = self.state.hash(x);
+        let h2 = splitmix64(&mut h1.clone()) | 1;
+        let w = self.w;
+        (0..self.d).map(move |r| {
+            let c = h1.wrapping_add((r as u64).wrapping_mul(h2)) % w as u64;
+            r * w + c as usize
+        })
+    }
+
+    pub fn add<T: Hash + ?Sized>(&mut self, x: &T, count: u64) {
+        for i in self.cells(x) {
+            self.t[i] = self.t[i].saturating_add(count);
+        }
+        self.n = self.n.saturating_add(count);
+    }
+
+    /// 各行のカウンタの最小値
+    pub fn estimate<T: Hash + ?Sized>(&self, x: &T) -> u64 {
+        self.cells(x).map(|i| self.t[i]).min().unwrap_or(0)
+    }
+
+    pub fn is_compatible(&self, other: &Self) -> bool {
+        self.w == other.w && self.d == other.d && self.state == other.state
+    }
+
+    /// 同じパラメータ・キーで作ったシャードのカウンタを足し合わせる
+    pub fn merge(&mut self, other: &Self) -> bool {
+        if !self.is_compatible(other) {
+            return false;
+        }
+        for (a, &b) in self.t.iter_mut().zip(other.t.iter()) {
+            *a = a.saturating_add(b);
+        }
+        self.n = self.n.saturating_add(other.n);
+        true
+    }
+}
+
+/// HyperLogLog
+///
+/// レジスタ数m = 2^pのとき、相対標準誤差は約1.04 / sqrt(m)
+///
+/// registers: ハッシュ値の上位pビットで選んだレジスタに、残りのビットの先頭の0の数 + 1の最大値を持つ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HyperLogLog {
+    registers: Box<[u8]>,
+    p: u32,
+    state: HashState,
+}
+
+impl HyperLogLog {
+    pub fn new(p: u32) -> Self {
+        Self::with_hash_state(p, HashState::new())
+    }
+
+    pub fn with_seed(p: u32, seed: u64) -> Self {
+        Self::with_hash_state(p, HashState::with_seed(seed))
+    }
+
+    pub fn with_hash_state(p: u32, state: HashState) -> Self {
+        assert!((4..=18).contains(&p));
+        Self {
+            registers: allocate_heap(1 << p),
+            p,
+            state,
+        }
+    }
+
+    /// レジスタ数m
+    pub fn len(&self) -> usize {
+        self.registers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.registers.iter().all(|&r| r == 0)
+    }
+
+    /// 相対標準誤差 1.04 / sqrt(m)
+    pub fn standard_error(&self) -> f64 {
+        1.04 / (self.len() as f64).sqrt()
+    }
+
+    pub fn add<T: Hash + ?Sized>(&mut self, x: &T) {
+        let h = self.state.hash(x);
+        let j = (h >> (64 - self.p)) as usize;
+        let rest = h << self.p;
+        let rho = std::cmp::min(rest.leading_zeros(), 64 - self.p) as u8 + 1;
+        if self.registers[j] < rho {
+            self.registers[j] = rho;
+        }
+    }
+
+    /// 異なる要素の数の推定値
+    /// 小さいうちは空のレジスタの数からlinear countingで推定する
+    pub fn estimate(&self) -> f64 {
+        let m = self.len() as f64;
+        let alpha = match self.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1. + 1.079 / m),
+        };
+
+        let sum: f64 = self.registers.iter().map(|&r| 2f64.powi(-(r as i32))).sum();
+        let e = alpha * m * m / sum;
+
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        if e <= 2.5 * m && zeros > 0 {
+            m * (m / zeros as f64).ln()
+        } else {
+            e
+        }
+    }
+
+    pub fn is_compatible(&self, other: &Self) -> bool {
+        self.p == other.p && self.state == other.state
+    }
+
+    /// 同じp・キーで作ったシャードのレジスタの最大値をとる
+    /// 結果は両方の要素の和集合に対するHyperLogLogと一致する
+    pub fn merge(&mut self, other: &Self) -> bool {
+        if !self.is_compatible(other) {
+            return false;
+        }
+        for (a, &b) in self.registers.iter_mut().zip(other.registers.iter()) {
+            *a = std::cmp::max(*a, b);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod test_sketch {
+    use super::*;
+
+    #[test]
+    fn test_count_min_error_bound() {
+        let (epsilon, delta) = (0.001, 0.01);
+        let mut cms = CountMinSketch::with_seed(epsilon, delta, 11);
+        assert_eq!(cms.width(), 2719);
+        assert_eq!(cms.depth(), 5);
+
+        // iの頻度を10000 / (i + 1)にした偏った流れ
+        let counts: Vec<u64> = (0..5000).map(|i| 10000 / (i + 1) + 1).collect();
+        for (i, &c) in counts.iter().enumerate() {
+            cms.add(&i, c);
+        }
+
+        let bound = cms.error_bound();
+        assert!((bound - epsilon * cms.total() as f64).abs() < 1.);
+        let mut over = 0;
+        for (i, &c) in counts.iter().enumerate() {
+            let e = cms.estimate(&i);
+            assert!(e >= c);
+            if (e - c) as f64 > bound {
+                over += 1;
+            }
+        }
+        assert!((over as f64) <= delta * counts.len() as f64);
+    }
+
+    #[test]
+    fn test_count_min_merge() {
+        let mut a = CountMinSketch::with_seed(0.01, 0.01, 1);
+        let mut b = CountMinSketch::with_seed(0.01, 0.01, 1);
+        a.add("x", 3);
+        b.add("x", 4);
+        b.add("y", 1);
+        assert!(a.merge(&b));
+        assert!(a.estimate("x") >= 7);
+        assert_eq!(a.total(), 8);
+        assert!(!a.merge(&CountMinSketch::with_seed(0.01, 0.01, 2)));
+
+        // 総数もカウンタも飽和する
+        a.add("x", u64::MAX);
+        assert_eq!((a.total(), a.estimate("x")), (u64::MAX, u64::MAX));
+        for &(e, d) in &[(0., 0.01), (0.01, 0.), (1.5, 0.01), (f64::NAN, 0.5)] {
+            assert!(std::panic::catch_unwind(|| CountMinSketch::params(e, d)).is_err());
+        }
+        let h = HashState::with_seed(1);
+        assert!(
+            std::panic::catch_unwind(|| CountMinSketch::with_params(usize::MAX, 2, h)).is_err()
+        );
+    }
+
+    #[test]
+    fn test_hyperloglog_error_bound() {
+        for &(p, n) in &[(10, 100), (12, 100_000), (14, 200_000)] {
+            let mut hll = HyperLogLog::with_seed(p, p as u64);
+            for i in 0..n {
+                hll.add(&i);
+                hll.add(&i);
+            }
+            let rel = (hll.estimate() - n as f64).abs() / n as f64;
+            // 標準誤差の3倍に収まる
+            assert!(rel < 3. * hll.standard_error(), "p: {}, rel: {}", p, rel);
+        }
+    }
+
+    #[test]
+    fn test_hyperloglog_merge() {
+        let mut a = HyperLogLog::with_seed(12, 0);
+        let mut b = HyperLogLog::with_seed(12, 0);
+        let mut all = HyperLogLog::with_seed(12, 0);
+        for i in 0..30_000 {
+            a.add(&i);
+            all.add(&i);
+        }
+        for i in 20_000..50_000 {
+            b.add(&i);
+            all.add(&i);
+        }
+        assert!(a.merge(&b));
+        assert_eq!(a, all);
+        let rel = (a.estimate() - 50_000.).abs() / 50_000.;
+        assert!(rel < 3. * a.standard_error());
+        assert!(!a.merge(&HyperLogLog::with_seed(10, 0)));
+    }
+}
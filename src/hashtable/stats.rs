@@ -0,0 +1,77 @@
+/// ハッシュテーブルの状態
+/// チェイン法ではバケットごとのチェイン長、オープンアドレス法では要素ごとの探索距離を集計する
+///
+/// histogram[k]: チェイン長(探索距離)がkのものの個数
+#[derive(Debug, Clone, PartialEq)]
+pub struct HashStats {
+    pub size: usize,
+    pub capacity: usize,
+    pub load_factor: f64,
+    pub max_probe: usize,
+    pub mean_probe: f64,
+    pub tombstones: usize,
+    pub resizes: usize,
+    pub histogram: Vec<usize>,
+}
+
+impl HashStats {
+    pub fn new<I>(
+        size: usize,
+        capacity: usize,
+        probes: I,
+        tombstones: usize,
+        resizes: usize,
+    ) -> Self
+    where
+        I: IntoIterator<Item = usize>,
+    {
+        let mut histogram = vec![];
+        let mut total = 0;
+        let mut count = 0;
+        for p in probes {
+            if histogram.len() <= p {
+                histogram.resize(p + 1, 0);
+            }
+            histogram[p] += 1;
+            total += p;
+            count += 1;
+        }
+
+        Self {
+            size,
+            capacity,
+            load_factor: if capacity == 0 {
+                0.
+            } else {
+                size as f64 / capacity as f64
+            },
+            max_probe: histogram.len().saturating_sub(1),
+            mean_probe: if count == 0 {
+                0.
+            } else {
+                total as f64 / count as f64
+            },
+            tombstones,
+            resizes,
+            histogram,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_stats {
+    use super::*;
+
+    #[test]
+    fn test_histogram() {
+        let s = HashStats::new(6, 8, vec![0, 0, 1, 3, 0, 2], 1, 2);
+        assert_eq!(s.histogram, vec![3, 1, 1, 1]);
+        assert_eq!(s.max_probe, 3);
+        assert_eq!(s.mean_probe, 1.);
+        assert_eq!(s.load_factor, 0.75);
+
+        let empty = HashStats::new(0, 0, vec![], 0, 0);
+        assert_eq!(empty.max_probe, 0);
+        assert_eq!(empty.mean_probe, 0.);
+    }
+}
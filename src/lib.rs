@@ -1,3 +1,6 @@
 pub mod array;
 pub mod hashtable;
 pub mod list;
+pub(crate) mod range;
+pub mod rng;
+pub mod tree;
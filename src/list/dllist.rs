@@ -19,6 +19,14 @@ impl<T> Node<T> {
     pub fn into_element(self: Box<Self>) -> T {
         self.x
     }
+
+    pub fn element(&self) -> &T {
+        &self.x
+    }
+
+    pub fn element_mut(&mut self) -> &mut T {
+        &mut self.x
+    }
 }
 
 pub struct Cursor<'a, T: 'a> {
@@ -97,7 +105,13 @@ impl<T> DLList<T> {
         }
     }
 
-    fn push_front_node(&mut self, mut node: Box<Node<T>>) {
+    /// nodeの所有権はリストに移る
+    ///
+    /// # Safety
+    /// リストはこのノードを自分のものとして扱い、pop_back_nodeやDropで解放する
+    /// nodeのnext/prevは上書きするので、他のリストに入っているノードの中身を
+    /// Box::from_rawで取り出したものを渡すときは、先にそのリストからunlink_nodeしておくこと
+    pub(crate) unsafe fn push_front_node(&mut self, mut node: Box<Node<T>>) {
         unsafe {
             node.next = self.head;
             node.prev = None;
@@ -130,7 +144,13 @@ impl<T> DLList<T> {
         }
     }
 
-    fn unlink_node(&mut self, mut node: NonNull<Node<T>>) {
+    /// ノードをリストから外すだけで、解放はしない
+    ///
+    /// # Safety
+    /// nodeはこのリストに入っていて、まだ解放されていないこと
+    /// 外したノードはどこからも解放されないので、呼び出し側がBox::from_rawで引き取るか、
+    /// push_front_nodeで入れ直すこと
+    pub(crate) unsafe fn unlink_node(&mut self, mut node: NonNull<Node<T>>) {
         let node = unsafe { node.as_mut() };
 
         match node.prev {
@@ -146,6 +166,24 @@ impl<T> DLList<T> {
         self.n -= 1;
     }
 
+    /// 外したノードの所有権を返す
+    ///
+    /// # Safety
+    /// head_nodeなどで取っておいたこのノードへのポインタは、これ以降使えなくなる
+    /// 呼び出し側は、そうしたポインタを自分の持つ索引(LruCacheならmap)から消すこと
+    pub(crate) unsafe fn pop_back_node(&mut self) -> Option<Box<Node<T>>> {
+        self.tail.map(|node| unsafe {
+            self.unlink_node(node);
+            Box::from_raw(node.as_ptr())
+        })
+    }
+
+    /// 先頭のノード(push_front_nodeの後に呼べば入れたノードを指す)
+    /// ポインタを返すだけなので安全だが、参照できるのはそのノードがこのリストに入っている間だけ
+    pub(crate) fn head_node(&self) -> Option<NonNull<Node<T>>> {
+        self.head
+    }
+
     fn splice_node(
         &mut self,
         existing_prev: Option<NonNull<Node<T>>>,
@@ -191,6 +229,14 @@ impl<T> DLList<T> {
         self.n
     }
 
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            current: self.head,
+            remaining: self.n,
+            marker: PhantomData,
+        }
+    }
+
     pub fn get(&self, index: usize) -> Option<&T> {
         unsafe { self.get_node(index).map(|n| &(*n.as_ptr()).x) }
     }
@@ -203,7 +249,8 @@ impl<T> DLList<T> {
 
     pub fn push_front(&mut self, x: T) {
         let node = Box::new(Node::new(x));
-        self.push_front_node(node);
+        // 新しく作ったノードなので、他のリストには入っていない
+        unsafe { self.push_front_node(node) };
     }
 
     pub fn push_back(&mut self, x: T) {
@@ -230,13 +277,36 @@ impl<T> DLList<T> {
         let node = self.get_node(index);
 
         if let Some(node) = node {
-            self.unlink_node(node);
+            // get_nodeで見つけたので、このリストに入っている
+            unsafe { self.unlink_node(node) };
         }
 
         unsafe { node.map(|n| &(*n.as_ptr()).x) }
     }
 }
 
+/// 先頭から順に見ていくイテレータ
+pub struct Iter<'a, T: 'a> {
+    current: Option<NonNull<Node<T>>>,
+    remaining: usize,
+    marker: PhantomData<&'a Node<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.current.map(|cur| unsafe {
+            let node = &*cur.as_ptr();
+            self.current = node.next;
+            self.remaining -= 1;
+            &node.x
+        })
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -270,6 +340,7 @@ mod test {
         l.remove(1);
         assert_eq!(l.size(), slice.len());
         assert_eq!(l.get(1), Some(&2));
+        assert_eq!(l.iter().cloned().collect::<Vec<_>>(), vec![0, 2, 111, 444, 4]);
         for i in 0..l.size() {
             println!("i: {}, val: {:?}", i, &l.get(i))
         }
@@ -0,0 +1,231 @@
+use crate::hashtable::seeded::SeededHashMap;
+use crate::list::dllist::{DLList, Node};
+use std::hash::Hash;
+use std::ptr::NonNull;
+
+/// 追い出したときに呼ぶ関数
+pub type EvictCallback<K, V> = Box<dyn FnMut(K, V)>;
+
+/// DLListで使った順を、ハッシュマップでキーからノードへの対応を持つLRUキャッシュ
+/// リストの先頭が最近使ったもの、末尾が一番使っていないもの
+///
+/// list: (キー, 値)のノード
+/// map: キー -> listのノード
+/// capacity: 要素数の上限
+///
+/// mapにあるポインタはすべてlistに入っている生きたノードを指し、listのノードはすべてmapにある
+/// DLListのunsafeな*_node関数はこの対応を前提に呼ぶ
+pub struct LruCache<K, V> {
+    list: DLList<(K, V)>,
+    map: SeededHashMap<K, NonNull<Node<(K, V)>>>,
+    capacity: usize,
+    on_evict: Option<EvictCallback<K, V>>,
+}
+
+impl<K, V> LruCache<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            list: DLList::new(),
+            map: SeededHashMap::new(),
+            capacity,
+            on_evict: None,
+        }
+    }
+
+    /// putやset_capacityで追い出すたびに呼ばれる(pop_lruやremoveでは呼ばない)
+    pub fn set_on_evict<F>(&mut self, f: F)
+    where
+        F: FnMut(K, V) + 'static,
+    {
+        self.on_evict = Some(Box::new(f));
+    }
+
+    pub fn size(&self) -> usize {
+        self.list.size()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn contains(&self, k: &K) -> bool {
+        self.map.contains_key(k)
+    }
+
+    /// ノードを外して先頭に付け直す(nodeはmapから取ったもの)
+    fn promote(&mut self, node: NonNull<Node<(K, V)>>) {
+        // mapにあるノードはlistに入っていて生きている。外したものをそのまま入れ直す
+        unsafe {
+            self.list.unlink_node(node);
+            self.list.push_front_node(Box::from_raw(node.as_ptr()));
+        }
+    }
+
+    /// 使ったことにして先頭に移す
+    pub fn get(&mut self, k: &K) -> Option<&V> {
+        let node = *self.map.get(k)?;
+        self.promote(node);
+        unsafe { Some(&(*node.as_ptr()).element().1) }
+    }
+
+    pub fn get_mut(&mut self, k: &K) -> Option<&mut V> {
+        let node = *self.map.get(k)?;
+        self.promote(node);
+        unsafe { Some(&mut (*node.as_ptr()).element_mut().1) }
+    }
+
+    /// 順番を変えずに見る
+    pub fn peek(&self, k: &K) -> Option<&V> {
+        let node = self.map.get(k)?;
+        unsafe { Some(&(*node.as_ptr()).element().1) }
+    }
+
+    /// すでにkがあれば値を置き換えて古い値を返す
+    /// 上限を超えたら一番使っていないものを追い出す
+    /// 上限が0のときは何も持たず、入れた(k, v)をそのまま追い出しの関数に渡す
+    pub fn put(&mut self, k: K, v: V) -> Option<V> {
+        if let Some(&node) = self.map.get(&k) {
+            self.promote(node);
+            let x = unsafe { (*node.as_ptr()).element_mut() };
+            return Some(std::mem::replace(&mut x.1, v));
+        }
+
+        if self.capacity == 0 {
+            self.evict(k, v);
+            return None;
+        }
+
+        while self.size() >= self.capacity {
+            if let Some((k, v)) = self.pop_lru() {
+                self.evict(k, v);
+            }
+        }
+
+        // 新しいノードを入れ、その先頭のポインタをmapに持つ(listから外すときにmapからも消す)
+        unsafe {
+            self.list
+                .push_front_node(Box::new(Node::new((k.clone(), v))));
+        }
+        if let Some(node) = self.list.head_node() {
+            self.map.insert(k, node);
+        }
+        None
+    }
+
+    /// 一番使っていないものを取り出す
+    pub fn pop_lru(&mut self) -> Option<(K, V)> {
+        // 外したノードへのポインタはすぐにmapから消す
+        let node = unsafe { self.list.pop_back_node() }?;
+        let (k, v) = node.into_element();
+        self.map.remove(&k);
+        Some((k, v))
+    }
+
+    pub fn remove(&mut self, k: &K) -> Option<V> {
+        let node = self.map.remove(k)?;
+        // mapにあったのでlistに入っている。外したノードはここで引き取る
+        let (_, v) = unsafe {
+            self.list.unlink_node(node);
+            Box::from_raw(node.as_ptr())
+        }
+        .into_element();
+        Some(v)
+    }
+
+    /// 上限を変える。小さくしたときは使っていない順に追い出す
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.size() > capacity {
+            if let Some((k, v)) = self.pop_lru() {
+                self.evict(k, v);
+            }
+        }
+    }
+
+    fn evict(&mut self, k: K, v: V) {
+        if let Some(f) = self.on_evict.as_mut() {
+            f(k, v);
+        }
+    }
+
+    /// 最近使った順
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.list.iter().map(|(k, v)| (k, v))
+    }
+}
+
+impl<K, V> Drop for LruCache<K, V> {
+    fn drop(&mut self) {
+        // mapも一緒に捨てるので、残ったポインタは使われない
+        while unsafe { self.list.pop_back_node() }.is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod test_lru_cache {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_get_put() {
+        let mut c = LruCache::new(2);
+        assert_eq!(c.put("a", 1), None);
+        assert_eq!(c.put("b", 2), None);
+        assert_eq!(c.get(&"a"), Some(&1));
+
+        // bが一番使っていない
+        c.put("c", 3);
+        assert!(!c.contains(&"b"));
+        assert_eq!(
+            c.iter().map(|(k, _)| *k).collect::<Vec<_>>(),
+            vec!["c", "a"]
+        );
+
+        // peekでは順番が変わらない
+        assert_eq!(c.peek(&"a"), Some(&1));
+        assert_eq!(c.put("c", 30), Some(3));
+        *c.get_mut(&"a").unwrap() += 10;
+        assert_eq!(c.pop_lru(), Some(("c", 30)));
+        assert_eq!(c.remove(&"a"), Some(11));
+        assert!(c.is_empty());
+    }
+
+    #[test]
+    fn test_evict_callback() {
+        let evicted = Rc::new(RefCell::new(vec![]));
+        let mut c = LruCache::new(3);
+        let log = Rc::clone(&evicted);
+        c.set_on_evict(move |k, v| log.borrow_mut().push((k, v)));
+
+        for i in 0..5 {
+            c.put(i, i * i);
+        }
+        c.get(&2);
+        assert_eq!(*evicted.borrow(), vec![(0, 0), (1, 1)]);
+
+        c.set_capacity(1);
+        assert_eq!(*evicted.borrow(), vec![(0, 0), (1, 1), (3, 9), (4, 16)]);
+        assert_eq!(c.size(), 1);
+        assert_eq!(c.peek(&2), Some(&4));
+
+        c.set_capacity(2);
+        c.put(5, 25);
+        assert_eq!(c.size(), 2);
+        assert_eq!(evicted.borrow().len(), 4);
+
+        // 上限0では入れたものがすぐに追い出される
+        c.set_capacity(0);
+        assert_eq!(evicted.borrow()[4..], [(2, 4), (5, 25)]);
+        assert_eq!(c.put(6, 36), None);
+        assert!(c.is_empty() && !c.contains(&6));
+        assert_eq!(evicted.borrow().last(), Some(&(6, 36)));
+    }
+}
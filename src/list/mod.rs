@@ -1,3 +1,6 @@
 pub mod dllist;
+pub mod lru_cache;
+pub mod rope;
 pub mod selist;
 pub mod slist;
+pub mod treap_list;
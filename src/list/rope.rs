@@ -0,0 +1,553 @@
+use crate::range::to_range;
+use crate::rng::Rng;
+use crate::tree::node::{BinaryNode, InOrder};
+use crate::tree::treap_core::{merge, TreapNode};
+use std::fmt;
+use std::ops::RangeBounds;
+
+/// チャンクの文字数の既定の上限
+const DEFAULT_CHUNK_SIZE: usize = 64;
+
+type Tree = Option<Box<Node>>;
+
+/// text: チャンク
+/// p: 優先度(小さいほど根に近い)
+/// chars, lines: このチャンクの文字数と改行の数
+/// sum_*: 部分木全体の文字数、バイト数、改行の数
+#[derive(Debug)]
+struct Node {
+    text: String,
+    p: u64,
+    chars: usize,
+    lines: usize,
+    sum_chars: usize,
+    sum_bytes: usize,
+    sum_lines: usize,
+    left: Tree,
+    right: Tree,
+}
+
+fn sum_chars(u: &Tree) -> usize {
+    u.as_ref().map_or(0, |u| u.sum_chars)
+}
+
+fn sum_bytes(u: &Tree) -> usize {
+    u.as_ref().map_or(0, |u| u.sum_bytes)
+}
+
+fn sum_lines(u: &Tree) -> usize {
+    u.as_ref().map_or(0, |u| u.sum_lines)
+}
+
+fn count_lines(s: &str) -> usize {
+    s.bytes().filter(|&c| c == b'\n').count()
+}
+
+/// i文字目のバイト位置
+fn byte_offset(s: &str, i: usize) -> usize {
+    s.char_indices().nth(i).map_or(s.len(), |(b, _)| b)
+}
+
+impl Node {
+    fn new(text: String, p: u64) -> Box<Self> {
+        let mut u = Box::new(Self {
+            text,
+            p,
+            chars: 0,
+            lines: 0,
+            sum_chars: 0,
+            sum_bytes: 0,
+            sum_lines: 0,
+            left: None,
+            right: None,
+        });
+        u.recount();
+        u
+    }
+
+    /// チャンクを書き換えたあとに数え直す
+    fn recount(&mut self) {
+        self.chars = self.text.chars().count();
+        self.lines = count_lines(&self.text);
+        self.update();
+    }
+}
+
+impl BinaryNode for Node {
+    fn left(&self) -> &Tree {
+        &self.left
+    }
+
+    fn right(&self) -> &Tree {
+        &self.right
+    }
+
+    fn left_mut(&mut self) -> &mut Tree {
+        &mut self.left
+    }
+
+    fn right_mut(&mut self) -> &mut Tree {
+        &mut self.right
+    }
+
+    fn update(&mut self) {
+        self.sum_chars = self.chars + sum_chars(&self.left) + sum_chars(&self.right);
+        self.sum_bytes = self.text.len() + sum_bytes(&self.left) + sum_bytes(&self.right);
+        self.sum_lines = self.lines + sum_lines(&self.left) + sum_lines(&self.right);
+    }
+}
+
+impl TreapNode for Node {
+    fn p(&self) -> u64 {
+        self.p
+    }
+}
+
+/// 前のi文字と残りに分ける
+/// チャンクの途中で分けるときは、後ろ半分を同じ優先度の新しいノードにする
+fn split(u: Tree, i: usize) -> (Tree, Tree) {
+    let mut node = match u {
+        None => return (None, None),
+        Some(node) => node,
+    };
+    let sl = sum_chars(&node.left);
+    if i <= sl {
+        let (l, r) = split(node.left.take(), i);
+        node.left = r;
+        node.update();
+        (l, Some(node))
+    } else if i >= sl + node.chars {
+        let (l, r) = split(node.right.take(), i - sl - node.chars);
+        node.right = l;
+        node.update();
+        (Some(node), r)
+    } else {
+        let b = byte_offset(&node.text, i - sl);
+        let mut m = Node::new(node.text.split_off(b), node.p);
+        m.right = node.right.take();
+        m.update();
+        node.recount();
+        (Some(node), Some(m))
+    }
+}
+
+/// 位置iを含むチャンクを探すときの進み方
+/// チャンクの境目では前のチャンクの末尾とみなす
+enum Step {
+    Left,
+    Here(usize),
+    Right(usize),
+}
+
+fn step(u: &Node, i: usize) -> Step {
+    let sl = sum_chars(&u.left);
+    if i < sl || (i == sl && sl > 0) {
+        Step::Left
+    } else if i <= sl + u.chars {
+        Step::Here(i - sl)
+    } else {
+        Step::Right(i - sl - u.chars)
+    }
+}
+
+/// UTF-8の文字列を、文字数がb以下のチャンクに分けてTreapに並べたもの
+/// 位置はすべて文字(char)単位、行は'\n'で区切る
+/// r: 根
+/// b: チャンクの文字数の上限
+/// rng: 優先度を作る
+#[derive(Debug)]
+pub struct Rope {
+    r: Tree,
+    b: usize,
+    rng: Rng,
+}
+
+impl Rope {
+    pub fn new() -> Self {
+        Self::with_chunk_size(DEFAULT_CHUNK_SIZE)
+    }
+
+    /// 優先度の乱数を固定する
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_rng(DEFAULT_CHUNK_SIZE, Rng::with_seed(seed))
+    }
+
+    pub fn with_chunk_size(b: usize) -> Self {
+        Self::with_rng(b, Rng::new())
+    }
+
+    fn with_rng(b: usize, rng: Rng) -> Self {
+        assert!(b > 0, "chunk size must be positive");
+        Self { r: None, b, rng }
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.b
+    }
+
+    pub fn len_chars(&self) -> usize {
+        sum_chars(&self.r)
+    }
+
+    pub fn len_bytes(&self) -> usize {
+        sum_bytes(&self.r)
+    }
+
+    /// 行の数(改行の数 + 1)
+    pub fn len_lines(&self) -> usize {
+        sum_lines(&self.r) + 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.r.is_none()
+    }
+
+    /// textをb文字以下のチャンクに均等に分けて木を作る
+    /// チャンクが2つ以上なら、どれもb / 2文字以上になる
+    fn build(&mut self, text: &str) -> Tree {
+        let n = text.chars().count();
+        let c = n.div_ceil(self.b);
+        let mut t = None;
+        let mut rest = text;
+        for j in 0..c {
+            let len = n / c + if j < n % c { 1 } else { 0 };
+            let k = byte_offset(rest, len);
+            let u = Node::new(rest[..k].to_string(), self.rng.next_u64());
+            t = merge(t, Some(u));
+            rest = &rest[k..];
+        }
+        t
+    }
+
+    /// lの後ろにrをつなぐ
+    /// 境目のチャンクがb / 2文字より少なければ、隣のチャンクとまとめて分け直す
+    /// (片方が空なら、同じ側の隣とまとめる)
+    /// 分け直したチャンクがまだ短ければ、さらにその隣とまとめる
+    fn join(&mut self, l: Tree, r: Tree) -> Tree {
+        let last = last_chunk(&l).map_or(0, |u| u.chars);
+        let first = first_chunk(&r).map_or(0, |u| u.chars);
+        let min = self.b / 2;
+        if (last == 0 || last >= min) && (first == 0 || first >= min) {
+            return merge(l, r);
+        }
+        if first == 0 {
+            let n = sum_chars(&l);
+            let (l, a) = split(l, n - last);
+            return if l.is_none() { a } else { self.join(l, a) };
+        }
+        if last == 0 {
+            let (c, r) = split(r, first);
+            return if r.is_none() { c } else { self.join(c, r) };
+        }
+        let n = sum_chars(&l);
+        let (l, a) = split(l, n - last);
+        let (c, r) = split(r, first);
+        let mut text = a.unwrap().text;
+        text.push_str(&c.unwrap().text);
+        let m = self.build(&text);
+        let lm = self.join(l, m);
+        self.join(lm, r)
+    }
+
+    /// 位置iの前にtextを入れる
+    /// 入る先のチャンクに収まればそのチャンクを書き換え、収まらなければ分けて新しいチャンクをつなぐ
+    /// 分けて短くなったチャンクはjoinで隣とまとめる
+    pub fn insert(&mut self, i: usize, text: &str) {
+        assert!(i <= self.len_chars(), "index out of bounds");
+        if text.is_empty() {
+            return;
+        }
+        let chars = text.chars().count();
+
+        let mut w = &self.r;
+        let mut fits = false;
+        let mut j = i;
+        while let Some(u) = w {
+            match step(u, j) {
+                Step::Left => w = &u.left,
+                Step::Right(k) => {
+                    j = k;
+                    w = &u.right;
+                }
+                Step::Here(_) => {
+                    fits = u.chars + chars <= self.b;
+                    break;
+                }
+            }
+        }
+
+        if fits {
+            let (bytes, lines) = (text.len(), count_lines(text));
+            let mut w = &mut self.r;
+            let mut j = i;
+            while let Some(u) = w {
+                u.sum_chars += chars;
+                u.sum_bytes += bytes;
+                u.sum_lines += lines;
+                match step(u, j) {
+                    Step::Left => w = &mut u.left,
+                    Step::Right(k) => {
+                        j = k;
+                        w = &mut u.right;
+                    }
+                    Step::Here(k) => {
+                        let b = byte_offset(&u.text, k);
+                        u.text.insert_str(b, text);
+                        u.chars += chars;
+                        u.lines += lines;
+                        break;
+                    }
+                }
+            }
+        } else {
+            let m = self.build(text);
+            let (l, r) = split(self.r.take(), i);
+            let lm = self.join(l, m);
+            self.r = self.join(lm, r);
+        }
+    }
+
+    /// 範囲の文字を取り除く
+    /// 境目のチャンクが短くなったらjoinで隣とまとめる
+    pub fn remove<R: RangeBounds<usize>>(&mut self, range: R) {
+        let (lo, hi) = to_range(range, self.len_chars());
+        if lo == hi {
+            return;
+        }
+        let (l, r) = split(self.r.take(), hi);
+        let (l, _) = split(l, lo);
+        self.r = self.join(l, r);
+    }
+
+    /// 範囲の文字を新しいStringにする
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> String {
+        fn collect(u: &Tree, lo: usize, hi: usize, offset: usize, out: &mut String) {
+            let u = match u {
+                Some(u) => u,
+                None => return,
+            };
+            let start = offset + sum_chars(&u.left);
+            let end = start + u.chars;
+            if lo < start {
+                collect(&u.left, lo, hi, offset, out);
+            }
+            let (a, b) = (std::cmp::max(lo, start), std::cmp::min(hi, end));
+            if a < b {
+                let s = byte_offset(&u.text, a - start);
+                let t = byte_offset(&u.text, b - start);
+                out.push_str(&u.text[s..t]);
+            }
+            if hi > end {
+                collect(&u.right, lo, hi, end, out);
+            }
+        }
+
+        let (lo, hi) = to_range(range, self.len_chars());
+        let mut out = String::new();
+        collect(&self.r, lo, hi, 0, &mut out);
+        out
+    }
+
+    /// 位置iが何行目か(0から)
+    pub fn char_to_line(&self, mut i: usize) -> usize {
+        assert!(i <= self.len_chars(), "index out of bounds");
+        let mut w = &self.r;
+        let mut line = 0;
+        while let Some(u) = w {
+            let sl = sum_chars(&u.left);
+            if i < sl {
+                w = &u.left;
+            } else if i <= sl + u.chars {
+                let b = byte_offset(&u.text, i - sl);
+                return line + sum_lines(&u.left) + count_lines(&u.text[..b]);
+            } else {
+                line += sum_lines(&u.left) + u.lines;
+                i -= sl + u.chars;
+                w = &u.right;
+            }
+        }
+        line
+    }
+
+    /// line行目(0から)の先頭の位置
+    pub fn line_to_char(&self, line: usize) -> Option<usize> {
+        if line == 0 {
+            return Some(0);
+        }
+        if line > sum_lines(&self.r) {
+            return None;
+        }
+        // line個目の改行の次の位置
+        let mut k = line;
+        let mut offset = 0;
+        let mut w = &self.r;
+        while let Some(u) = w {
+            let ll = sum_lines(&u.left);
+            if k <= ll {
+                w = &u.left;
+                continue;
+            }
+            k -= ll;
+            let sl = sum_chars(&u.left);
+            if k <= u.lines {
+                let idx = u
+                    .text
+                    .chars()
+                    .enumerate()
+                    .filter(|&(_, c)| c == '\n')
+                    .nth(k - 1)
+                    .map(|(j, _)| j)
+                    .unwrap();
+                return Some(offset + sl + idx + 1);
+            }
+            k -= u.lines;
+            offset += sl + u.chars;
+            w = &u.right;
+        }
+        None
+    }
+
+    /// チャンクを前から順に
+    pub fn chunks(&self) -> Chunks<'_> {
+        Chunks {
+            nodes: InOrder::new(self.r.as_deref()),
+        }
+    }
+}
+
+fn first_chunk(u: &Tree) -> Option<&Node> {
+    let mut u = u.as_ref()?;
+    while let Some(l) = &u.left {
+        u = l;
+    }
+    Some(u)
+}
+
+fn last_chunk(u: &Tree) -> Option<&Node> {
+    let mut u = u.as_ref()?;
+    while let Some(r) = &u.right {
+        u = r;
+    }
+    Some(u)
+}
+
+impl Default for Rope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<&str> for Rope {
+    fn from(text: &str) -> Self {
+        let mut rope = Self::new();
+        rope.r = rope.build(text);
+        rope
+    }
+}
+
+impl fmt::Display for Rope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for chunk in self.chunks() {
+            f.write_str(chunk)?;
+        }
+        Ok(())
+    }
+}
+
+/// チャンクを中間順に返すイテレータ
+pub struct Chunks<'a> {
+    nodes: InOrder<&'a Node>,
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = &'a str;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.nodes.next().map(|u| u.text.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test_rope {
+    use super::*;
+
+    /// どのチャンクもb / 2文字以上b文字以下(チャンクが1つだけなら短くてもよい)
+    fn check_chunks(rope: &Rope) {
+        let sizes: Vec<usize> = rope.chunks().map(|c| c.chars().count()).collect();
+        assert!(sizes.iter().all(|&k| k <= rope.b), "{:?}", sizes);
+        assert!(
+            sizes.len() == 1 || sizes.iter().all(|&k| k >= rope.b / 2),
+            "{:?}",
+            sizes
+        );
+    }
+
+    #[test]
+    fn test_edit() {
+        let mut rope = Rope::with_rng(4, Rng::with_seed(1));
+        let mut model = String::new();
+        let words = ["a", "あいう", "\n", "hello, world", "é\nü", "", "漢字\n"];
+        let mut rng = Rng::with_seed(5);
+        for i in 0..2000 {
+            let n = model.chars().count();
+            let at = rng.gen_range(n + 1);
+            if i % 3 == 2 && n > 0 {
+                let len = std::cmp::min(rng.gen_range(7), n - at);
+                rope.remove(at..at + len);
+                let (a, b) = (byte_offset(&model, at), byte_offset(&model, at + len));
+                model.replace_range(a..b, "");
+            } else {
+                let w = words[rng.gen_range(words.len())];
+                rope.insert(at, w);
+                model.insert_str(byte_offset(&model, at), w);
+            }
+            check_chunks(&rope);
+        }
+        assert_eq!(rope.to_string(), model);
+        assert_eq!(rope.len_chars(), model.chars().count());
+        assert_eq!(rope.len_bytes(), model.len());
+
+        // 1文字ずつ打ち込んでも、チャンクは細かくならない
+        let mut typed = Rope::with_rng(16, Rng::with_seed(3));
+        typed.insert(0, &"x".repeat(64));
+        for i in 0..1000 {
+            typed.insert(i * 7 % typed.len_chars(), "y");
+            check_chunks(&typed);
+        }
+        assert!(typed.chunks().count() <= 2 * typed.len_chars() / 16 + 1);
+
+        let n = rope.len_chars();
+        let (a, b) = (n / 3, 2 * n / 3);
+        let expected: String = model.chars().skip(a).take(b - a).collect();
+        assert_eq!(rope.slice(a..b), expected);
+        assert_eq!(rope.slice(..), model);
+        assert_eq!(rope.slice(b..=b), model.chars().nth(b).unwrap().to_string());
+    }
+
+    #[test]
+    fn test_lines() {
+        let text = "first\nsecond line\n\nあいうえお\nlast";
+        let mut rope = Rope::with_rng(3, Rng::with_seed(2));
+        rope.insert(0, text);
+        assert_eq!(rope.len_lines(), 5);
+
+        let starts = [0, 6, 18, 19, 25];
+        for (line, &start) in starts.iter().enumerate() {
+            assert_eq!(rope.line_to_char(line), Some(start));
+            assert_eq!(rope.char_to_line(start), line);
+        }
+        assert_eq!(rope.line_to_char(5), None);
+        assert_eq!(rope.char_to_line(5), 0);
+        assert_eq!(rope.char_to_line(rope.len_chars()), 4);
+
+        rope.remove(5..6);
+        assert_eq!(rope.len_lines(), 4);
+        assert_eq!(rope.line_to_char(1), Some(17));
+        assert_eq!(Rope::from("").len_lines(), 1);
+        assert_eq!(Rope::from(text).to_string(), text);
+
+        let mut seeded = Rope::with_seed(5);
+        seeded.insert(0, text);
+        assert_eq!(seeded.chunk_size(), DEFAULT_CHUNK_SIZE);
+        assert_eq!(seeded.line_to_char(4), Some(25));
+    }
+}
@@ -80,11 +80,11 @@ impl<'a, T:> Cursor<'a, T> {
     }
 
     pub fn current(&self) -> Option<&'a T> {
-        unsafe { self.current.map(|cur| &(*cur.as_ptr()).d[self.j]) }
+        unsafe { self.current.map(|cur| &(&(*cur.as_ptr()).d)[self.j]) }
     }
 
     pub fn current_mut(&mut self) -> Option<&'a mut T> {
-        unsafe { self.current.map(|cur| &mut (*cur.as_ptr()).d[self.j])}
+        unsafe { self.current.map(|cur| &mut (&mut (*cur.as_ptr()).d)[self.j])}
     }
 }
 
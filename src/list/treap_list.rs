@@ -0,0 +1,335 @@
+use crate::range::to_range;
+use crate::rng::Rng;
+use crate::tree::node::BinaryNode;
+use crate::tree::treap_core::{merge, TreapNode};
+use std::iter::FromIterator;
+use std::ops::{Index, IndexMut, RangeBounds};
+
+type Tree<T> = Option<Box<Node<T>>>;
+
+/// p: 優先度(小さいほど根に近い)
+/// size: 部分木のノード数、位置はこれから決まるのでキーは持たない
+/// rev: 部分木を左右反転する操作がまだ子に伝わっていない
+#[derive(Debug)]
+struct Node<T> {
+    x: T,
+    p: u64,
+    size: usize,
+    rev: bool,
+    left: Tree<T>,
+    right: Tree<T>,
+}
+
+fn size<T>(u: &Tree<T>) -> usize {
+    u.as_ref().map_or(0, |u| u.size)
+}
+
+impl<T> BinaryNode for Node<T> {
+    fn left(&self) -> &Tree<T> {
+        &self.left
+    }
+
+    fn right(&self) -> &Tree<T> {
+        &self.right
+    }
+
+    fn left_mut(&mut self) -> &mut Tree<T> {
+        &mut self.left
+    }
+
+    fn right_mut(&mut self) -> &mut Tree<T> {
+        &mut self.right
+    }
+
+    fn update(&mut self) {
+        self.size = 1 + size(&self.left) + size(&self.right);
+    }
+}
+
+impl<T> TreapNode for Node<T> {
+    fn p(&self) -> u64 {
+        self.p
+    }
+
+    /// 反転を子に伝える
+    fn push(&mut self) {
+        if self.rev {
+            std::mem::swap(&mut self.left, &mut self.right);
+            for c in self.left.iter_mut().chain(self.right.iter_mut()) {
+                c.rev ^= true;
+            }
+            self.rev = false;
+        }
+    }
+}
+
+/// 前のi個と残りに分ける
+fn split<T>(u: Tree<T>, i: usize) -> (Tree<T>, Tree<T>) {
+    match u {
+        None => (None, None),
+        Some(mut node) => {
+            node.push();
+            let sl = size(&node.left);
+            if i <= sl {
+                let (l, r) = split(node.left.take(), i);
+                node.left = r;
+                node.update();
+                (l, Some(node))
+            } else {
+                let (l, r) = split(node.right.take(), i - sl - 1);
+                node.right = l;
+                node.update();
+                (Some(node), r)
+            }
+        }
+    }
+}
+
+/// キーの代わりに部分木の大きさで位置を決めるTreap(implicit treap)によるリスト
+/// 位置での参照、挿入、削除、分割、連結、区間の反転がO(log n)
+/// r: 根
+/// rng: 優先度を作る
+#[derive(Debug)]
+pub struct TreapList<T> {
+    r: Tree<T>,
+    rng: Rng,
+}
+
+impl<T> TreapList<T> {
+    pub fn new() -> Self {
+        Self::with_rng(Rng::new())
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_rng(Rng::with_seed(seed))
+    }
+
+    fn with_rng(rng: Rng) -> Self {
+        Self { r: None, rng }
+    }
+
+    /// 内部要素の数
+    pub fn size(&self) -> usize {
+        size(&self.r)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.r.is_none()
+    }
+
+    /// まだ伝えていない反転を考えながら下りる
+    pub fn get(&self, mut i: usize) -> Option<&T> {
+        let mut w = &self.r;
+        let mut flip = false;
+        while let Some(u) = w {
+            flip ^= u.rev;
+            let (l, r) = if flip {
+                (&u.right, &u.left)
+            } else {
+                (&u.left, &u.right)
+            };
+            let sl = size(l);
+            if i < sl {
+                w = l;
+            } else if i == sl {
+                return Some(&u.x);
+            } else {
+                i -= sl + 1;
+                w = r;
+            }
+        }
+        None
+    }
+
+    /// 反転を伝えながら下りる
+    pub fn get_mut(&mut self, mut i: usize) -> Option<&mut T> {
+        let mut w = &mut self.r;
+        while let Some(u) = w {
+            u.push();
+            let sl = size(&u.left);
+            if i < sl {
+                w = &mut u.left;
+            } else if i == sl {
+                return Some(&mut u.x);
+            } else {
+                i -= sl + 1;
+                w = &mut u.right;
+            }
+        }
+        None
+    }
+
+    /// 位置iの値を置き換えて古い値を返す
+    pub fn set(&mut self, i: usize, x: T) -> T {
+        let y = self.get_mut(i).expect("index out of bounds");
+        std::mem::replace(y, x)
+    }
+
+    /// ArrayStackと同じく、iがsize以上なら末尾に入れる
+    pub fn add(&mut self, i: usize, x: T) {
+        let p = self.rng.next_u64();
+        let u = Some(Box::new(Node {
+            x,
+            p,
+            size: 1,
+            rev: false,
+            left: None,
+            right: None,
+        }));
+        let (l, r) = split(self.r.take(), i);
+        self.r = merge(merge(l, u), r);
+    }
+
+    pub fn push_back(&mut self, x: T) {
+        self.add(self.size(), x);
+    }
+
+    pub fn remove(&mut self, i: usize) -> T {
+        assert!(i < self.size(), "index out of bounds");
+        let (l, r) = split(self.r.take(), i);
+        let (m, r) = split(r, 1);
+        self.r = merge(l, r);
+        m.unwrap().x
+    }
+
+    /// 位置i以降を取り出して新しいTreapListにする
+    pub fn split_at(&mut self, i: usize) -> Self {
+        let (l, r) = split(self.r.take(), i);
+        self.r = l;
+        let mut t = Self::with_rng(self.rng.fork());
+        t.r = r;
+        t
+    }
+
+    /// otherを後ろにつなぐ
+    pub fn concat(&mut self, mut other: Self) {
+        self.r = merge(self.r.take(), other.r.take());
+    }
+
+    /// 範囲を取り出し、根に反転の印をつけて戻す
+    pub fn reverse<R: RangeBounds<usize>>(&mut self, range: R) {
+        let (lo, hi) = to_range(range, self.size());
+        let (l, r) = split(self.r.take(), hi);
+        let (l, mut m) = split(l, lo);
+        if let Some(m) = m.as_mut() {
+            m.rev ^= true;
+        }
+        self.r = merge(merge(l, m), r);
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        let mut it = Iter { stack: vec![] };
+        it.push_left(&self.r, false);
+        it
+    }
+}
+
+impl<T> Default for TreapList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> FromIterator<T> for TreapList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut l = Self::new();
+        for x in iter {
+            l.push_back(x);
+        }
+        l
+    }
+}
+
+impl<T> Index<usize> for TreapList<T> {
+    type Output = T;
+    fn index(&self, index: usize) -> &Self::Output {
+        self.get(index).expect("index out of bounds")
+    }
+}
+
+impl<T> IndexMut<usize> for TreapList<T> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        self.get_mut(index).expect("index out of bounds")
+    }
+}
+
+/// 反転の印を数えながら、左(反転していれば右)の子を積んでいく
+pub struct Iter<'a, T> {
+    stack: Vec<(&'a Node<T>, bool)>,
+}
+
+impl<'a, T> Iter<'a, T> {
+    fn push_left(&mut self, mut w: &'a Tree<T>, mut flip: bool) {
+        while let Some(u) = w {
+            flip ^= u.rev;
+            self.stack.push((u, flip));
+            w = if flip { &u.right } else { &u.left };
+        }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        let (u, flip) = self.stack.pop()?;
+        self.push_left(if flip { &u.left } else { &u.right }, flip);
+        Some(&u.x)
+    }
+}
+
+#[cfg(test)]
+mod test_treap_list {
+    use super::*;
+
+    #[test]
+    fn test_add_remove() {
+        let mut l = TreapList::with_seed(1);
+        let mut v = vec![];
+        let mut rng = Rng::with_seed(2);
+        for i in 0..1000 {
+            let j = rng.gen_range(v.len() + 1);
+            l.add(j, i);
+            v.insert(j, i);
+        }
+        l.add(5000, -1);
+        v.push(-1);
+        assert_eq!(l.size(), v.len());
+        assert!(l.iter().eq(v.iter()));
+
+        for _ in 0..500 {
+            let j = rng.gen_range(v.len());
+            assert_eq!(l.remove(j), v.remove(j));
+        }
+        for (i, x) in v.iter().enumerate() {
+            assert_eq!(l[i], *x);
+        }
+        assert_eq!(l.set(3, 100), v[3]);
+        l[4] += 1;
+        assert_eq!((l[3], l[4]), (100, v[4] + 1));
+        assert_eq!(l.get(v.len()), None);
+    }
+
+    #[test]
+    fn test_split_concat_reverse() {
+        let mut l: TreapList<_> = (0..10).collect();
+        l.reverse(2..6);
+        assert!(l.iter().cloned().eq(vec![0, 1, 5, 4, 3, 2, 6, 7, 8, 9]));
+        l.reverse(..);
+        assert!(l.iter().cloned().eq(vec![9, 8, 7, 6, 2, 3, 4, 5, 1, 0]));
+        l.reverse(3..=5);
+        assert_eq!(l[3], 3);
+        assert_eq!(l[5], 6);
+
+        // 反転が伝わっていない状態で分けてつなぐ
+        l.reverse(1..9);
+        let mut r = l.split_at(4);
+        assert_eq!(l.size(), 4);
+        assert_eq!(r.size(), 6);
+        let expected: Vec<_> = l.iter().chain(r.iter()).cloned().collect();
+        r.add(0, 42);
+        l.concat(r);
+        assert_eq!(l.size(), 11);
+        assert_eq!(l.remove(4), 42);
+        assert!(l.iter().cloned().eq(expected));
+    }
+}
@@ -0,0 +1,17 @@
+use std::ops::{Bound, RangeBounds};
+
+/// 範囲を[lo, hi)にする
+pub(crate) fn to_range<R: RangeBounds<usize>>(range: R, n: usize) -> (usize, usize) {
+    let lo = match range.start_bound() {
+        Bound::Included(&i) => i,
+        Bound::Excluded(&i) => i + 1,
+        Bound::Unbounded => 0,
+    };
+    let hi = match range.end_bound() {
+        Bound::Included(&i) => i + 1,
+        Bound::Excluded(&i) => i,
+        Bound::Unbounded => n,
+    };
+    assert!(lo <= hi && hi <= n, "range out of bounds");
+    (lo, hi)
+}
@@ -0,0 +1,64 @@
+use crate::hashtable::hash::{random_seed, splitmix64};
+
+/// 乱択データ構造用の小さな擬似乱数生成器(xorshift64*)
+/// 同じシードなら同じ列を返すので、テストで再現できる
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new() -> Self {
+        Self::with_seed(random_seed())
+    }
+
+    pub fn with_seed(mut seed: u64) -> Self {
+        // 0は不動点なので避ける
+        Self {
+            state: splitmix64(&mut seed) | 1,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// [0, n)の一様乱数
+    pub fn gen_range(&mut self, n: usize) -> usize {
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
+    }
+
+    /// この生成器から独立したシードの生成器を作る
+    pub fn fork(&mut self) -> Self {
+        Self::with_seed(self.next_u64())
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test_rng {
+    use super::*;
+
+    #[test]
+    fn test_seed() {
+        let mut a = Rng::with_seed(0);
+        let mut b = Rng::with_seed(0);
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+
+        let mut counts = [0; 10];
+        for _ in 0..100_000 {
+            counts[a.gen_range(10)] += 1;
+        }
+        assert!(counts.iter().all(|&c| (9_000..11_000).contains(&c)));
+    }
+}
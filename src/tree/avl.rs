@@ -0,0 +1,312 @@
+use crate::tree::node::{rotate_left, rotate_right, BinaryNode, InOrder};
+use crate::tree::SSet;
+use std::cmp::Ordering;
+use std::iter::FromIterator;
+
+type Tree<T> = Option<Box<Node<T>>>;
+
+/// h: 部分木の高さ(葉は0)
+#[derive(Debug, Clone)]
+struct Node<T> {
+    x: T,
+    h: isize,
+    left: Tree<T>,
+    right: Tree<T>,
+}
+
+fn height<T>(u: &Tree<T>) -> isize {
+    u.as_ref().map_or(-1, |u| u.h)
+}
+
+impl<T> BinaryNode for Node<T> {
+    fn left(&self) -> &Tree<T> {
+        &self.left
+    }
+
+    fn right(&self) -> &Tree<T> {
+        &self.right
+    }
+
+    fn left_mut(&mut self) -> &mut Tree<T> {
+        &mut self.left
+    }
+
+    fn right_mut(&mut self) -> &mut Tree<T> {
+        &mut self.right
+    }
+
+    fn update(&mut self) {
+        self.h = 1 + std::cmp::max(height(&self.left), height(&self.right));
+    }
+}
+
+impl<T> Node<T> {
+    /// 左が高ければ正
+    fn balance(&self) -> isize {
+        height(&self.left) - height(&self.right)
+    }
+}
+
+/// 左右の高さの差が2になっていたら、1回か2回の回転で戻す
+fn rebalance<T>(u: &mut Box<Node<T>>) {
+    u.update();
+    match u.balance() {
+        2 => {
+            let l = u.left.as_mut().unwrap();
+            if l.balance() < 0 {
+                rotate_left(l);
+            }
+            rotate_right(u);
+        }
+        -2 => {
+            let r = u.right.as_mut().unwrap();
+            if r.balance() > 0 {
+                rotate_right(r);
+            }
+            rotate_left(u);
+        }
+        _ => {}
+    }
+}
+
+fn add<T: Ord>(u: &mut Tree<T>, x: T) -> bool {
+    let node = match u {
+        None => {
+            *u = Some(Box::new(Node {
+                x,
+                h: 0,
+                left: None,
+                right: None,
+            }));
+            return true;
+        }
+        Some(node) => node,
+    };
+    let added = match x.cmp(&node.x) {
+        Ordering::Less => add(&mut node.left, x),
+        Ordering::Greater => add(&mut node.right, x),
+        Ordering::Equal => false,
+    };
+    if added {
+        rebalance(node);
+    }
+    added
+}
+
+fn remove_min<T>(u: &mut Tree<T>) -> T {
+    let node = u.as_mut().unwrap();
+    if node.left.is_none() {
+        let node = *u.take().unwrap();
+        *u = node.right;
+        return node.x;
+    }
+    let x = remove_min(&mut node.left);
+    rebalance(node);
+    x
+}
+
+fn remove<T: Ord>(u: &mut Tree<T>, x: &T) -> Option<T> {
+    let node = u.as_mut()?;
+    let removed = match x.cmp(&node.x) {
+        Ordering::Less => remove(&mut node.left, x),
+        Ordering::Greater => remove(&mut node.right, x),
+        Ordering::Equal => {
+            if node.right.is_none() {
+                let node = *u.take().unwrap();
+                *u = node.left;
+                return Some(node.x);
+            }
+            // 右の部分木の最小と入れ替える
+            let y = remove_min(&mut node.right);
+            Some(std::mem::replace(&mut node.x, y))
+        }
+    };
+    if removed.is_some() {
+        rebalance(node);
+    }
+    removed
+}
+
+/// どのノードでも左右の部分木の高さの差が1以下の二分探索木
+/// r: 根
+/// n: 要素数
+#[derive(Debug, Clone)]
+pub struct AvlTree<T> {
+    r: Tree<T>,
+    n: usize,
+}
+
+impl<T> AvlTree<T> {
+    pub fn new() -> Self {
+        Self { r: None, n: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    pub fn height(&self) -> isize {
+        height(&self.r)
+    }
+
+    /// 昇順
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            nodes: InOrder::new(self.r.as_deref()),
+        }
+    }
+
+    /// 高さの記録と釣り合いの条件が守られているか調べる
+    /// 守られていなければ、最初に見つかった違反の説明を返す
+    pub fn check_invariants(&self) -> Result<(), String>
+    where
+        T: Ord + std::fmt::Debug,
+    {
+        fn rec<T: Ord + std::fmt::Debug>(u: &Tree<T>) -> Result<(isize, usize), String> {
+            let node = match u {
+                None => return Ok((-1, 0)),
+                Some(node) => node,
+            };
+            if let Some(l) = &node.left {
+                if l.x >= node.x {
+                    return Err(format!("{:?} is left of {:?}", l.x, node.x));
+                }
+            }
+            if let Some(r) = &node.right {
+                if r.x <= node.x {
+                    return Err(format!("{:?} is right of {:?}", r.x, node.x));
+                }
+            }
+            let (hl, nl) = rec(&node.left)?;
+            let (hr, nr) = rec(&node.right)?;
+            if (hl - hr).abs() > 1 {
+                return Err(format!("{:?}: heights {} and {}", node.x, hl, hr));
+            }
+            let h = 1 + std::cmp::max(hl, hr);
+            if h != node.h {
+                return Err(format!("{:?}: stored height {} != {}", node.x, node.h, h));
+            }
+            Ok((h, 1 + nl + nr))
+        }
+
+        let (_, n) = rec(&self.r)?;
+        if n != self.n {
+            return Err(format!("size {} != {}", self.n, n));
+        }
+        Ok(())
+    }
+}
+
+impl<T: Ord> SSet<T> for AvlTree<T> {
+    fn size(&self) -> usize {
+        self.n
+    }
+
+    fn add(&mut self, x: T) -> bool {
+        let added = add(&mut self.r, x);
+        if added {
+            self.n += 1;
+        }
+        added
+    }
+
+    fn remove(&mut self, x: &T) -> Option<T> {
+        let removed = remove(&mut self.r, x);
+        if removed.is_some() {
+            self.n -= 1;
+        }
+        removed
+    }
+
+    fn find(&self, x: &T) -> Option<&T> {
+        let mut w = &self.r;
+        let mut z = None;
+        while let Some(u) = w {
+            match x.cmp(&u.x) {
+                Ordering::Less => {
+                    z = Some(&u.x);
+                    w = &u.left;
+                }
+                Ordering::Greater => w = &u.right,
+                Ordering::Equal => return Some(&u.x),
+            }
+        }
+        z
+    }
+}
+
+impl<T> Default for AvlTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> FromIterator<T> for AvlTree<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut t = Self::new();
+        for x in iter {
+            t.add(x);
+        }
+        t
+    }
+}
+
+/// 中間順のイテレータ
+#[derive(Debug, Clone)]
+pub struct Iter<'a, T> {
+    nodes: InOrder<&'a Node<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.nodes.next().map(|u| &u.x)
+    }
+}
+
+#[cfg(test)]
+mod test_avl {
+    use super::*;
+    use crate::rng::Rng;
+
+    #[test]
+    fn test_rotations() {
+        // 左左、右右、左右、右左のそれぞれの形で回転が起きる
+        for order in [[3, 2, 1], [1, 2, 3], [3, 1, 2], [1, 3, 2]].iter() {
+            let t: AvlTree<i32> = order.iter().cloned().collect();
+            assert_eq!(t.check_invariants(), Ok(()));
+            assert_eq!(t.height(), 1);
+            assert_eq!(t.r.as_ref().unwrap().x, 2);
+        }
+    }
+
+    #[test]
+    fn test_sset() {
+        let mut t = AvlTree::new();
+        let mut rng = Rng::with_seed(7);
+        let mut v = vec![];
+        for _ in 0..2000 {
+            let x = rng.gen_range(5000);
+            assert_eq!(t.add(x), !v.contains(&x));
+            assert_eq!(t.check_invariants(), Ok(()));
+            v.push(x);
+        }
+        v.sort();
+        v.dedup();
+        assert_eq!(t.size(), v.len());
+        // 高さは1.44 log n程度
+        assert!(t.height() <= 16);
+
+        for (i, x) in v.iter().enumerate() {
+            if i % 3 != 0 {
+                assert_eq!(t.remove(x), Some(*x));
+                assert_eq!(t.check_invariants(), Ok(()));
+            }
+        }
+        assert_eq!(t.remove(&v[1]), None);
+        let rest: Vec<_> = v.iter().step_by(3).cloned().collect();
+        assert!(t.iter().cloned().eq(rest.iter().cloned()));
+        assert_eq!(t.find(&(v[1])), Some(&v[3]));
+        assert_eq!(t.find(&5000), None);
+    }
+}
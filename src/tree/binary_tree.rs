@@ -0,0 +1,387 @@
+use crate::array::queue::ArrayQueue;
+use std::{marker::PhantomData, ptr::NonNull};
+
+pub type NodePtr<T> = NonNull<Node<T>>;
+
+#[derive(Debug)]
+pub struct Node<T> {
+    pub(crate) x: T,
+    pub(crate) left: Option<NodePtr<T>>,
+    pub(crate) right: Option<NodePtr<T>>,
+    pub(crate) parent: Option<NodePtr<T>>,
+}
+
+impl<T> Node<T> {
+    pub fn new(x: T) -> Self {
+        Self {
+            x,
+            left: None,
+            right: None,
+            parent: None,
+        }
+    }
+
+    pub(crate) fn leak(x: T) -> NodePtr<T> {
+        Box::leak(Box::new(Self::new(x))).into()
+    }
+
+    pub fn element(&self) -> &T {
+        &self.x
+    }
+}
+
+/// 親へのポインタだけで木をたどるときの、どこから来たか
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Came {
+    /// 親から来た(初めて訪れた)
+    Parent,
+    /// 左の子から戻ってきた
+    Left,
+    /// 右の子から戻ってきた
+    Right,
+}
+
+/// 本のtraverse2: スタックも再帰も使わずに、親へのポインタでuの部分木をたどる
+/// ノードに着くたびにf(ノード, 来た方向, 深さ)を呼ぶ
+pub(crate) fn walk<T, F>(u: Option<NodePtr<T>>, mut f: F)
+where
+    F: FnMut(NodePtr<T>, Came, usize),
+{
+    let top = match u {
+        Some(u) => u,
+        None => return,
+    };
+    let stop = unsafe { top.as_ref().parent };
+
+    let mut u = Some(top);
+    let mut prev = stop;
+    let mut depth = 0;
+    while let Some(cur) = u {
+        let node = unsafe { cur.as_ref() };
+        let came = if prev == node.parent && (prev != stop || cur == top) {
+            Came::Parent
+        } else if prev.is_some() && prev == node.left {
+            Came::Left
+        } else {
+            Came::Right
+        };
+        f(cur, came, depth);
+
+        let next = match came {
+            Came::Parent if node.left.is_some() => node.left,
+            Came::Parent | Came::Left if node.right.is_some() => node.right,
+            _ => node.parent,
+        };
+
+        if cur == top && next == stop {
+            break;
+        }
+        if next == node.parent {
+            depth -= 1;
+        } else {
+            depth += 1;
+        }
+        prev = Some(cur);
+        u = next;
+    }
+}
+
+/// 親をたどって根までの辺の数を数える
+pub(crate) fn depth<T>(u: NodePtr<T>) -> usize {
+    let mut d = 0;
+    let mut u = unsafe { u.as_ref().parent };
+    while let Some(p) = u {
+        d += 1;
+        u = unsafe { p.as_ref().parent };
+    }
+    d
+}
+
+/// uの部分木のノード数
+pub(crate) fn size<T>(u: Option<NodePtr<T>>) -> usize {
+    let mut n = 0;
+    walk(u, |_, came, _| {
+        if came == Came::Parent {
+            n += 1
+        }
+    });
+    n
+}
+
+/// uの部分木の高さ(空なら-1)
+pub(crate) fn height<T>(u: Option<NodePtr<T>>) -> isize {
+    let mut h = -1;
+    walk(u, |_, _, d| h = std::cmp::max(h, d as isize));
+    h
+}
+
+/// uの部分木のノードをすべて解放する
+pub(crate) fn free<T>(u: Option<NodePtr<T>>) {
+    let mut stack: Vec<NodePtr<T>> = u.into_iter().collect();
+    while let Some(node) = stack.pop() {
+        let node = unsafe { Box::from_raw(node.as_ptr()) };
+        stack.extend(node.left);
+        stack.extend(node.right);
+    }
+}
+
+/// 親へのポインタを持つ二分木
+/// r: 根
+#[derive(Debug)]
+pub struct BinaryTree<T> {
+    pub(crate) r: Option<NodePtr<T>>,
+    marker: PhantomData<Box<Node<T>>>,
+}
+
+impl<T> BinaryTree<T> {
+    pub fn new() -> Self {
+        Self {
+            r: None,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn leaf(x: T) -> Self {
+        Self {
+            r: Some(Node::leak(x)),
+            marker: PhantomData,
+        }
+    }
+
+    /// xを根にして、leftとrightを左右の部分木にする
+    pub fn join(x: T, mut left: Self, mut right: Self) -> Self {
+        let r = Node::leak(x);
+        unsafe {
+            for (child, side) in [(left.r.take(), 0), (right.r.take(), 1)] {
+                if let Some(mut c) = child {
+                    c.as_mut().parent = Some(r);
+                    if side == 0 {
+                        (*r.as_ptr()).left = Some(c);
+                    } else {
+                        (*r.as_ptr()).right = Some(c);
+                    }
+                }
+            }
+        }
+        Self {
+            r: Some(r),
+            marker: PhantomData,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.r.is_none()
+    }
+
+    pub fn root(&self) -> Option<&T> {
+        self.r.map(|r| unsafe { &(*r.as_ptr()).x })
+    }
+
+    /// ノード数
+    pub fn size(&self) -> usize {
+        size(self.r)
+    }
+
+    /// 根から葉までの辺の数の最大値(空なら-1)
+    pub fn height(&self) -> isize {
+        height(self.r)
+    }
+
+    /// 先行順で最初に見つかったxの深さ
+    pub fn depth(&self, x: &T) -> Option<usize>
+    where
+        T: PartialEq,
+    {
+        let mut found = None;
+        walk(self.r, |u, came, _| unsafe {
+            if found.is_none() && came == Came::Parent && u.as_ref().x == *x {
+                found = Some(u);
+            }
+        });
+        found.map(depth)
+    }
+
+    pub fn preorder<F: FnMut(&T)>(&self, mut f: F) {
+        walk(self.r, |u, came, _| {
+            if came == Came::Parent {
+                f(unsafe { &(*u.as_ptr()).x })
+            }
+        });
+    }
+
+    pub fn inorder<F: FnMut(&T)>(&self, mut f: F) {
+        walk(self.r, |u, came, _| unsafe {
+            let node = u.as_ref();
+            let visit = match came {
+                Came::Parent => node.left.is_none(),
+                Came::Left => true,
+                Came::Right => false,
+            };
+            if visit {
+                f(&(*u.as_ptr()).x)
+            }
+        });
+    }
+
+    pub fn postorder<F: FnMut(&T)>(&self, mut f: F) {
+        walk(self.r, |u, came, _| unsafe {
+            let node = u.as_ref();
+            let visit = match came {
+                Came::Parent => node.left.is_none() && node.right.is_none(),
+                Came::Left => node.right.is_none(),
+                Came::Right => true,
+            };
+            if visit {
+                f(&(*u.as_ptr()).x)
+            }
+        });
+    }
+
+    pub fn preorder_recursive<F: FnMut(&T)>(&self, mut f: F) {
+        fn rec<T, F: FnMut(&T)>(u: Option<NodePtr<T>>, f: &mut F) {
+            if let Some(u) = u {
+                let node = unsafe { &*u.as_ptr() };
+                f(&node.x);
+                rec(node.left, f);
+                rec(node.right, f);
+            }
+        }
+        rec(self.r, &mut f);
+    }
+
+    pub fn inorder_recursive<F: FnMut(&T)>(&self, mut f: F) {
+        fn rec<T, F: FnMut(&T)>(u: Option<NodePtr<T>>, f: &mut F) {
+            if let Some(u) = u {
+                let node = unsafe { &*u.as_ptr() };
+                rec(node.left, f);
+                f(&node.x);
+                rec(node.right, f);
+            }
+        }
+        rec(self.r, &mut f);
+    }
+
+    pub fn postorder_recursive<F: FnMut(&T)>(&self, mut f: F) {
+        fn rec<T, F: FnMut(&T)>(u: Option<NodePtr<T>>, f: &mut F) {
+            if let Some(u) = u {
+                let node = unsafe { &*u.as_ptr() };
+                rec(node.left, f);
+                rec(node.right, f);
+                f(&node.x);
+            }
+        }
+        rec(self.r, &mut f);
+    }
+
+    /// 幅優先探索: 根から近い順、同じ深さなら左から
+    pub fn bfs<F: FnMut(&T)>(&self, mut f: F) {
+        let mut q: ArrayQueue<Option<NodePtr<T>>> = ArrayQueue::new(1);
+        if self.r.is_some() {
+            q.add(self.r);
+        }
+        while q.size() > 0 {
+            if let Some(u) = q.remove() {
+                let node = unsafe { &*u.as_ptr() };
+                f(&node.x);
+                if node.left.is_some() {
+                    q.add(node.left);
+                }
+                if node.right.is_some() {
+                    q.add(node.right);
+                }
+            }
+        }
+    }
+}
+
+impl<T> Default for BinaryTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for BinaryTree<T> {
+    fn drop(&mut self) {
+        free(self.r.take());
+    }
+}
+
+#[cfg(test)]
+mod test_binary_tree {
+    use super::*;
+
+    ///       1
+    ///      / \
+    ///     2   3
+    ///    / \   \
+    ///   4   5   6
+    ///      /
+    ///     7
+    fn sample() -> BinaryTree<i32> {
+        let l = BinaryTree::leaf;
+        let e = BinaryTree::new;
+        BinaryTree::join(
+            1,
+            BinaryTree::join(2, l(4), BinaryTree::join(5, l(7), e())),
+            BinaryTree::join(3, e(), l(6)),
+        )
+    }
+
+    fn collect(f: impl Fn(&mut dyn FnMut(&i32))) -> Vec<i32> {
+        let mut v = vec![];
+        f(&mut |x| v.push(*x));
+        v
+    }
+
+    #[test]
+    fn test_traversals() {
+        let t = sample();
+        let pre = vec![1, 2, 4, 5, 7, 3, 6];
+        let ino = vec![4, 2, 7, 5, 1, 3, 6];
+        let post = vec![4, 7, 5, 2, 6, 3, 1];
+
+        assert_eq!(collect(|f| t.preorder(f)), pre);
+        assert_eq!(collect(|f| t.preorder_recursive(f)), pre);
+        assert_eq!(collect(|f| t.inorder(f)), ino);
+        assert_eq!(collect(|f| t.inorder_recursive(f)), ino);
+        assert_eq!(collect(|f| t.postorder(f)), post);
+        assert_eq!(collect(|f| t.postorder_recursive(f)), post);
+        assert_eq!(collect(|f| t.bfs(f)), vec![1, 2, 3, 4, 5, 6, 7]);
+
+        let empty: BinaryTree<i32> = BinaryTree::new();
+        assert!(collect(|f| empty.inorder(f)).is_empty());
+        assert_eq!(empty.height(), -1);
+    }
+
+    #[test]
+    fn test_size_height_depth() {
+        let t = sample();
+        assert_eq!(t.size(), 7);
+        assert_eq!(t.height(), 3);
+        assert_eq!(t.depth(&1), Some(0));
+        assert_eq!(t.depth(&6), Some(2));
+        assert_eq!(t.depth(&7), Some(3));
+        assert_eq!(t.depth(&8), None);
+        assert_eq!(t.root(), Some(&1));
+
+        // 部分木だけをたどる
+        let left = unsafe { t.r.unwrap().as_ref().left };
+        assert_eq!(size(left), 4);
+        assert_eq!(height(left), 2);
+    }
+
+    #[test]
+    fn test_deep_tree() {
+        // 再帰すると溢れる深さでも size, height, depth, drop は動く
+        let mut t = BinaryTree::leaf(0);
+        for i in 1..200_000 {
+            t = BinaryTree::join(i, t, BinaryTree::new());
+        }
+        assert_eq!(t.size(), 200_000);
+        assert_eq!(t.height(), 199_999);
+        assert_eq!(t.depth(&0), Some(199_999));
+        let mut n = 0;
+        t.inorder(|_| n += 1);
+        assert_eq!(n, 200_000);
+    }
+}
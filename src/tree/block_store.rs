@@ -0,0 +1,181 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// 同じ大きさのブロックを番号で読み書きする外部記憶
+pub trait BlockStore {
+    /// 1ブロックのバイト数
+    fn block_size(&self) -> usize;
+    /// i番目のブロックをbufに読む(bufの長さはブロックの大きさ)
+    fn read_block(&mut self, i: usize, buf: &mut [u8]) -> io::Result<()>;
+    /// i番目のブロックに書く、末尾より先なら伸ばす
+    fn write_block(&mut self, i: usize, buf: &[u8]) -> io::Result<()>;
+    /// 書いたものを記憶装置に確定させる
+    fn sync(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 呼び出し側が持ったまま貸すとき
+impl<S: BlockStore + ?Sized> BlockStore for &mut S {
+    fn block_size(&self) -> usize {
+        (**self).block_size()
+    }
+
+    fn read_block(&mut self, i: usize, buf: &mut [u8]) -> io::Result<()> {
+        (**self).read_block(i, buf)
+    }
+
+    fn write_block(&mut self, i: usize, buf: &[u8]) -> io::Result<()> {
+        (**self).write_block(i, buf)
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        (**self).sync()
+    }
+}
+
+/// 1つのファイルをブロックの並びとして使う
+/// i番目のブロックはファイルのi * block_sizeバイト目から
+#[derive(Debug)]
+pub struct FileBlockStore {
+    file: File,
+    block_size: usize,
+}
+
+impl FileBlockStore {
+    /// なければ作る、すでにあれば中身はそのまま
+    pub fn open<P: AsRef<Path>>(path: P, block_size: usize) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        Ok(Self { file, block_size })
+    }
+
+    /// ファイルにあるブロックの数
+    pub fn len(&self) -> io::Result<usize> {
+        Ok(self.file.metadata()?.len() as usize / self.block_size)
+    }
+
+    pub fn is_empty(&self) -> io::Result<bool> {
+        Ok(self.len()? == 0)
+    }
+}
+
+impl BlockStore for FileBlockStore {
+    fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    fn read_block(&mut self, i: usize, buf: &mut [u8]) -> io::Result<()> {
+        assert_eq!(buf.len(), self.block_size, "buffer must be one block");
+        self.file
+            .seek(SeekFrom::Start((i * self.block_size) as u64))?;
+        self.file.read_exact(buf)
+    }
+
+    fn write_block(&mut self, i: usize, buf: &[u8]) -> io::Result<()> {
+        assert_eq!(buf.len(), self.block_size, "buffer must be one block");
+        self.file
+            .seek(SeekFrom::Start((i * self.block_size) as u64))?;
+        self.file.write_all(buf)
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        self.file.sync_data()
+    }
+}
+
+/// テスト用にメモリ上に置く
+#[derive(Debug)]
+pub struct MemBlockStore {
+    blocks: Vec<Box<[u8]>>,
+    block_size: usize,
+}
+
+impl MemBlockStore {
+    pub fn new(block_size: usize) -> Self {
+        Self {
+            blocks: vec![],
+            block_size,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+}
+
+impl BlockStore for MemBlockStore {
+    fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// まだ書いていないブロックはファイルと同じくUnexpectedEofにする
+    fn read_block(&mut self, i: usize, buf: &mut [u8]) -> io::Result<()> {
+        assert_eq!(buf.len(), self.block_size, "buffer must be one block");
+        match self.blocks.get(i) {
+            Some(b) => {
+                buf.copy_from_slice(b);
+                Ok(())
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "block out of range",
+            )),
+        }
+    }
+
+    fn write_block(&mut self, i: usize, buf: &[u8]) -> io::Result<()> {
+        assert_eq!(buf.len(), self.block_size, "buffer must be one block");
+        while self.blocks.len() <= i {
+            self.blocks
+                .push(vec![0; self.block_size].into_boxed_slice());
+        }
+        self.blocks[i].copy_from_slice(buf);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_block_store {
+    use super::*;
+
+    fn round_trip<S: BlockStore>(s: &mut S) {
+        let bs = s.block_size();
+        let mut buf = vec![0; bs];
+        assert!(s.read_block(0, &mut buf).is_err());
+
+        s.write_block(2, &vec![7; bs]).unwrap();
+        s.write_block(0, &vec![1; bs]).unwrap();
+        s.read_block(2, &mut buf).unwrap();
+        assert!(buf.iter().all(|&x| x == 7));
+        s.read_block(0, &mut buf).unwrap();
+        assert!(buf.iter().all(|&x| x == 1));
+        // 間のブロックは0で埋まる
+        s.read_block(1, &mut buf).unwrap();
+        assert!(buf.iter().all(|&x| x == 0));
+        assert!(s.read_block(3, &mut buf).is_err());
+        s.sync().unwrap();
+    }
+
+    #[test]
+    fn test_stores() {
+        let mut m = MemBlockStore::new(32);
+        round_trip(&mut m);
+        assert_eq!(m.len(), 3);
+
+        let path = std::env::temp_dir().join(format!("block_store_{}", std::process::id()));
+        let mut f = FileBlockStore::open(&path, 32).unwrap();
+        round_trip(&mut f);
+        assert_eq!(f.len().unwrap(), 3);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,285 @@
+use crate::tree::binary_tree::{BinaryTree, Node, NodePtr};
+use crate::tree::SSet;
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+
+/// 釣り合いをとらない二分探索木
+/// tree: 親へのポインタを持つ二分木
+/// n: 要素数
+#[derive(Debug)]
+pub struct BinarySearchTree<T> {
+    pub(crate) tree: BinaryTree<T>,
+    pub(crate) n: usize,
+}
+
+impl<T> BinarySearchTree<T> {
+    pub fn new() -> Self {
+        Self {
+            tree: BinaryTree::new(),
+            n: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// 走査や高さはBinaryTreeのものを使う
+    pub fn tree(&self) -> &BinaryTree<T> {
+        &self.tree
+    }
+
+    /// 昇順
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            next: self.tree.r.map(leftmost),
+            marker: PhantomData,
+        }
+    }
+
+    /// pの子としてuをつなぐ、pがなければuを根にする
+    pub(crate) fn add_child(&mut self, p: Option<NodePtr<T>>, mut u: NodePtr<T>, left: bool) {
+        unsafe {
+            match p {
+                None => self.tree.r = Some(u),
+                Some(mut p) => {
+                    if left {
+                        p.as_mut().left = Some(u);
+                    } else {
+                        p.as_mut().right = Some(u);
+                    }
+                }
+            }
+            u.as_mut().parent = p;
+        }
+        self.n += 1;
+    }
+
+    /// 子が1つ以下のuを木から外す(解放はしない)
+    pub(crate) fn splice(&mut self, u: NodePtr<T>) {
+        unsafe {
+            let node = u.as_ref();
+            let s = if node.left.is_some() {
+                node.left
+            } else {
+                node.right
+            };
+
+            let p = node.parent;
+            match p {
+                None => self.tree.r = s,
+                Some(mut p) => {
+                    if p.as_ref().left == Some(u) {
+                        p.as_mut().left = s;
+                    } else {
+                        p.as_mut().right = s;
+                    }
+                }
+            }
+            if let Some(mut s) = s {
+                s.as_mut().parent = p;
+            }
+        }
+        self.n -= 1;
+    }
+
+    /// uの値を取り除いて返す
+    /// 子が2つあるときは右の部分木の最小値と値を入れ替えて、そちらを外す
+    /// 実際に外したノードの親を返す(ScapegoatTreeなどで使う)
+    pub(crate) fn remove_node(&mut self, mut u: NodePtr<T>) -> (T, Option<NodePtr<T>>) {
+        unsafe {
+            let target = if u.as_ref().left.is_some() && u.as_ref().right.is_some() {
+                let mut w = leftmost(u.as_ref().right.unwrap());
+                std::mem::swap(&mut u.as_mut().x, &mut w.as_mut().x);
+                w
+            } else {
+                u
+            };
+            let parent = target.as_ref().parent;
+            self.splice(target);
+            (Box::from_raw(target.as_ptr()).x, parent)
+        }
+    }
+}
+
+impl<T: Ord> BinarySearchTree<T> {
+    /// xを探したときに最後に見たノード(xがあればそのノード)
+    pub(crate) fn find_last(&self, x: &T) -> Option<NodePtr<T>> {
+        let mut w = self.tree.r;
+        let mut prev = None;
+        while let Some(u) = w {
+            prev = w;
+            let node = unsafe { u.as_ref() };
+            match x.cmp(&node.x) {
+                Ordering::Less => w = node.left,
+                Ordering::Greater => w = node.right,
+                Ordering::Equal => return w,
+            }
+        }
+        prev
+    }
+
+    /// xがなければ新しいノードを入れてそれを返す
+    pub(crate) fn add_node(&mut self, x: T) -> Option<NodePtr<T>> {
+        let p = self.find_last(&x);
+        let left = match p {
+            Some(p) => match x.cmp(unsafe { &p.as_ref().x }) {
+                Ordering::Less => true,
+                Ordering::Greater => false,
+                Ordering::Equal => return None,
+            },
+            None => true,
+        };
+        let u = Node::leak(x);
+        self.add_child(p, u, left);
+        Some(u)
+    }
+
+    /// xと等しい要素のノード
+    pub(crate) fn find_node(&self, x: &T) -> Option<NodePtr<T>> {
+        self.find_last(x).filter(|u| unsafe { u.as_ref().x == *x })
+    }
+}
+
+impl<T: Ord> SSet<T> for BinarySearchTree<T> {
+    fn size(&self) -> usize {
+        self.n
+    }
+
+    fn add(&mut self, x: T) -> bool {
+        self.add_node(x).is_some()
+    }
+
+    fn remove(&mut self, x: &T) -> Option<T> {
+        let u = self.find_node(x)?;
+        Some(self.remove_node(u).0)
+    }
+
+    fn find(&self, x: &T) -> Option<&T> {
+        let mut w = self.tree.r;
+        let mut z = None;
+        while let Some(u) = w {
+            let node = unsafe { &*u.as_ptr() };
+            match x.cmp(&node.x) {
+                Ordering::Less => {
+                    z = Some(&node.x);
+                    w = node.left;
+                }
+                Ordering::Greater => w = node.right,
+                Ordering::Equal => return Some(&node.x),
+            }
+        }
+        z
+    }
+}
+
+impl<T> Default for BinarySearchTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// uの部分木の最小のノード
+pub(crate) fn leftmost<T>(mut u: NodePtr<T>) -> NodePtr<T> {
+    while let Some(l) = unsafe { u.as_ref().left } {
+        u = l;
+    }
+    u
+}
+
+/// 中間順で次のノード
+pub(crate) fn successor<T>(u: NodePtr<T>) -> Option<NodePtr<T>> {
+    unsafe {
+        if let Some(r) = u.as_ref().right {
+            return Some(leftmost(r));
+        }
+        let mut u = u;
+        while let Some(p) = u.as_ref().parent {
+            if p.as_ref().left == Some(u) {
+                return Some(p);
+            }
+            u = p;
+        }
+        None
+    }
+}
+
+/// 親へのポインタで次のノードに進む昇順のイテレータ
+pub struct Iter<'a, T> {
+    next: Option<NodePtr<T>>,
+    marker: PhantomData<&'a T>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        let u = self.next?;
+        self.next = successor(u);
+        Some(unsafe { &(*u.as_ptr()).x })
+    }
+}
+
+#[cfg(test)]
+mod test_bst {
+    use super::*;
+    use crate::rng::Rng;
+
+    #[test]
+    fn test_add_find_remove() {
+        let mut t = BinarySearchTree::new();
+        for &x in &[7, 3, 11, 1, 5, 9, 13, 4, 6, 8, 12, 14] {
+            assert!(t.add(x));
+        }
+        assert!(!t.add(5));
+        assert_eq!(t.size(), 12);
+        assert_eq!(t.tree().height(), 3);
+
+        assert_eq!(t.find(&5), Some(&5));
+        assert_eq!(t.find(&10), Some(&11));
+        assert_eq!(t.find(&0), Some(&1));
+        assert_eq!(t.find(&15), None);
+
+        // 子が2つ
+        assert_eq!(t.remove(&3), Some(3));
+        // 根
+        assert_eq!(t.remove(&7), Some(7));
+        // 葉
+        assert_eq!(t.remove(&14), Some(14));
+        // 子が1つ
+        assert_eq!(t.remove(&13), Some(13));
+        assert_eq!(t.remove(&100), None);
+
+        assert_eq!(
+            t.iter().cloned().collect::<Vec<_>>(),
+            vec![1, 4, 5, 6, 8, 9, 11, 12]
+        );
+        assert_eq!(t.size(), 8);
+        assert_eq!(t.tree().size(), 8);
+    }
+
+    #[test]
+    fn test_sorted() {
+        let mut t = BinarySearchTree::new();
+        let mut rng = Rng::with_seed(1);
+        let mut v = vec![];
+        for _ in 0..500 {
+            let x = rng.gen_range(1000);
+            t.add(x);
+            v.push(x);
+        }
+        v.sort();
+        v.dedup();
+        assert!(t.iter().eq(v.iter()));
+
+        for y in v.iter().step_by(3) {
+            assert_eq!(t.remove(y), Some(*y));
+        }
+        let rest: Vec<_> = v
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| i % 3 != 0)
+            .map(|(_, y)| y)
+            .collect();
+        assert!(t.iter().eq(rest.into_iter()));
+    }
+}
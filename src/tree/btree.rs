@@ -0,0 +1,417 @@
+use crate::array::allocate_heap;
+use crate::tree::SSet;
+use std::iter::FromIterator;
+
+/// keys: 長さ2Bの配列、先頭n個を使う
+/// children: 長さ2B + 1の配列、葉なら空
+/// 根以外はB - 1個以上2B - 1個以下のキーを持つ(一時的に2B個になったら分ける)
+#[derive(Debug)]
+struct Node<T> {
+    keys: Box<[T]>,
+    children: Box<[Option<Box<Node<T>>>]>,
+    n: usize,
+}
+
+impl<T: Default + Clone> Node<T> {
+    fn new(b: usize) -> Box<Self> {
+        Box::new(Self {
+            keys: allocate_heap(2 * b),
+            children: allocate_heap(2 * b + 1),
+            n: 0,
+        })
+    }
+}
+
+impl<T> Node<T> {
+    fn is_leaf(&self) -> bool {
+        self.children[0].is_none()
+    }
+
+    /// i番目のキーの位置にxを、その右の子としてcを入れる
+    fn insert(&mut self, i: usize, x: T, c: Option<Box<Node<T>>>) {
+        let n = self.n;
+        self.keys[n] = x;
+        self.keys[i..=n].rotate_right(1);
+        self.children[n + 1] = c;
+        self.children[i + 1..=n + 1].rotate_right(1);
+        self.n += 1;
+    }
+
+    /// i番目のキーとその右の子を取り除く
+    fn remove(&mut self, i: usize) -> (T, Option<Box<Node<T>>>)
+    where
+        T: Default,
+    {
+        let n = self.n;
+        self.keys[i..n].rotate_left(1);
+        self.children[i + 1..=n].rotate_left(1);
+        self.n -= 1;
+        (
+            std::mem::take(&mut self.keys[n - 1]),
+            self.children[n].take(),
+        )
+    }
+
+    /// 先頭のキーとその左の子を取り除く
+    fn remove_front(&mut self) -> (T, Option<Box<Node<T>>>)
+    where
+        T: Default,
+    {
+        let n = self.n;
+        self.keys[..n].rotate_left(1);
+        self.children[..=n].rotate_left(1);
+        self.n -= 1;
+        (
+            std::mem::take(&mut self.keys[n - 1]),
+            self.children[n].take(),
+        )
+    }
+
+    /// 先頭にキーxとその左の子cを入れる
+    fn insert_front(&mut self, x: T, c: Option<Box<Node<T>>>) {
+        let n = self.n;
+        self.keys[n] = x;
+        self.keys[..=n].rotate_right(1);
+        self.children[n + 1] = c;
+        self.children[..=n + 1].rotate_right(1);
+        self.n += 1;
+    }
+}
+
+impl<T: Ord> Node<T> {
+    /// xがあればOk(位置)、なければErr(x以上で最小のキーの位置)
+    fn search(&self, x: &T) -> Result<usize, usize> {
+        self.keys[..self.n].binary_search(x)
+    }
+}
+
+/// 分けたときの(真ん中のキー, 新しい右のノード)
+type Split<T> = (T, Box<Node<T>>);
+
+/// 前半のB個を残し、真ん中のキーと、後半のB - 1個を持つ新しいノードを返す
+fn split<T: Default + Clone>(u: &mut Node<T>, b: usize) -> Split<T> {
+    let mut w = Node::new(b);
+    for j in b + 1..u.n {
+        w.keys[j - b - 1] = std::mem::take(&mut u.keys[j]);
+    }
+    for j in b + 1..=u.n {
+        w.children[j - b - 1] = u.children[j].take();
+    }
+    w.n = u.n - b - 1;
+    u.n = b;
+    (std::mem::take(&mut u.keys[b]), w)
+}
+
+/// 既にあればxをそのまま返す
+fn add<T: Ord + Default + Clone>(u: &mut Node<T>, x: T, b: usize) -> Result<Option<Split<T>>, T> {
+    let i = match u.search(&x) {
+        Ok(_) => return Err(x),
+        Err(i) => i,
+    };
+    if u.is_leaf() {
+        u.insert(i, x, None);
+    } else {
+        let c = u.children[i].as_mut().unwrap();
+        if let Some((y, w)) = add(c, x, b)? {
+            u.insert(i, y, Some(w));
+        }
+    }
+    if u.n == 2 * b {
+        Ok(Some(split(u, b)))
+    } else {
+        Ok(None)
+    }
+}
+
+/// 子iのキーがB - 1個より少なくなったら、兄弟から借りるか兄弟とまとめる
+fn check_underflow<T: Default + Clone>(u: &mut Node<T>, i: usize, b: usize) {
+    if u.children[i].as_ref().unwrap().n >= b - 1 {
+        return;
+    }
+    // 左に兄弟がいればそれを、いなければ右の兄弟を使う
+    let j = if i > 0 { i - 1 } else { i };
+    let (l, r) = u.children.split_at_mut(j + 1);
+    let left = l[j].as_mut().unwrap();
+    let right = r[0].as_mut().unwrap();
+
+    if i > 0 && left.n > b - 1 {
+        // 左の兄弟の最後のキーを親に上げ、親のキーを右に下ろす
+        let (y, c) = left.remove(left.n - 1);
+        let z = std::mem::replace(&mut u.keys[j], y);
+        right.insert_front(z, c);
+    } else if i == 0 && right.n > b - 1 {
+        let (y, c) = right.remove_front();
+        let z = std::mem::replace(&mut u.keys[j], y);
+        let n = left.n;
+        left.insert(n, z, c);
+    } else {
+        // 左 + 親のキー + 右 を左にまとめる
+        let (z, right) = u.remove(j);
+        let mut right = right.unwrap();
+        let left = u.children[j].as_mut().unwrap();
+        let n = left.n;
+        left.keys[n] = z;
+        for k in 0..right.n {
+            left.keys[n + 1 + k] = std::mem::take(&mut right.keys[k]);
+        }
+        for k in 0..=right.n {
+            left.children[n + 1 + k] = right.children[k].take();
+        }
+        left.n += 1 + right.n;
+    }
+}
+
+fn remove_smallest<T: Default + Clone>(u: &mut Node<T>, b: usize) -> T {
+    if u.is_leaf() {
+        return u.remove_front().0;
+    }
+    let x = remove_smallest(u.children[0].as_mut().unwrap(), b);
+    check_underflow(u, 0, b);
+    x
+}
+
+fn remove<T: Ord + Default + Clone>(u: &mut Node<T>, x: &T, b: usize) -> Option<T> {
+    match u.search(x) {
+        Ok(i) => {
+            if u.is_leaf() {
+                return Some(u.remove(i).0);
+            }
+            // 右の部分木の最小と入れ替える
+            let y = remove_smallest(u.children[i + 1].as_mut().unwrap(), b);
+            let x = std::mem::replace(&mut u.keys[i], y);
+            check_underflow(u, i + 1, b);
+            Some(x)
+        }
+        Err(i) => {
+            let c = u.children[i].as_mut()?;
+            let x = remove(c, x, b)?;
+            check_underflow(u, i, b);
+            Some(x)
+        }
+    }
+}
+
+/// 各ノードが2B個までのキーを固定長の配列で持つB木
+/// r: 根
+/// b: ノードの大きさを決める(B >= 2)
+/// n: 要素数
+#[derive(Debug)]
+pub struct BTree<T> {
+    r: Box<Node<T>>,
+    b: usize,
+    n: usize,
+}
+
+impl<T: Default + Clone> BTree<T> {
+    pub fn new(b: usize) -> Self {
+        assert!(b >= 2, "B must be at least 2");
+        Self {
+            r: Node::new(b),
+            b,
+            n: 0,
+        }
+    }
+}
+
+impl<T> BTree<T> {
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.b
+    }
+
+    /// 根から葉までの辺の数
+    pub fn height(&self) -> usize {
+        let mut h = 0;
+        let mut u = &self.r;
+        while let Some(c) = &u.children[0] {
+            h += 1;
+            u = c;
+        }
+        h
+    }
+
+    /// 昇順
+    pub fn iter(&self) -> Iter<'_, T> {
+        let mut it = Iter { stack: vec![] };
+        it.push_left(&self.r);
+        it
+    }
+}
+
+impl<T: Ord + Default + Clone> SSet<T> for BTree<T> {
+    fn size(&self) -> usize {
+        self.n
+    }
+
+    /// 根を分けたときは、真ん中のキーだけを持つ新しい根を作る
+    fn add(&mut self, x: T) -> bool {
+        match add(&mut self.r, x, self.b) {
+            Err(_) => return false,
+            Ok(None) => {}
+            Ok(Some((y, w))) => {
+                let old = std::mem::replace(&mut self.r, Node::new(self.b));
+                self.r.keys[0] = y;
+                self.r.children[0] = Some(old);
+                self.r.children[1] = Some(w);
+                self.r.n = 1;
+            }
+        }
+        self.n += 1;
+        true
+    }
+
+    /// 根のキーがなくなったら、唯一の子を根にする
+    fn remove(&mut self, x: &T) -> Option<T> {
+        let x = remove(&mut self.r, x, self.b)?;
+        if self.r.n == 0 {
+            if let Some(c) = self.r.children[0].take() {
+                self.r = c;
+            }
+        }
+        self.n -= 1;
+        Some(x)
+    }
+
+    fn find(&self, x: &T) -> Option<&T> {
+        let mut u = &self.r;
+        let mut z = None;
+        loop {
+            let i = match u.search(x) {
+                Ok(i) => return Some(&u.keys[i]),
+                Err(i) => i,
+            };
+            if i < u.n {
+                z = Some(&u.keys[i]);
+            }
+            match &u.children[i] {
+                Some(c) => u = c,
+                None => return z,
+            }
+        }
+    }
+}
+
+impl<T: Ord + Default + Clone> FromIterator<T> for BTree<T> {
+    /// B = 16で作る
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut t = Self::new(16);
+        for x in iter {
+            t.add(x);
+        }
+        t
+    }
+}
+
+/// (ノード, 次に返すキーの位置)を積んでいく中間順のイテレータ
+pub struct Iter<'a, T> {
+    stack: Vec<(&'a Node<T>, usize)>,
+}
+
+impl<'a, T> Iter<'a, T> {
+    fn push_left(&mut self, mut u: &'a Node<T>) {
+        loop {
+            self.stack.push((u, 0));
+            match &u.children[0] {
+                Some(c) => u = c,
+                None => break,
+            }
+        }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (u, i) = self.stack.pop()?;
+            if i < u.n {
+                self.stack.push((u, i + 1));
+                if let Some(c) = &u.children[i + 1] {
+                    self.push_left(c);
+                }
+                return Some(&u.keys[i]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_btree {
+    use super::*;
+    use crate::rng::Rng;
+    use std::collections::BTreeSet;
+
+    /// キーの数、並び、葉の深さがそろっていることを確かめて要素数を返す
+    fn check<T: Ord>(
+        u: &Node<T>,
+        b: usize,
+        is_root: bool,
+        depth: usize,
+        leaf: &mut Option<usize>,
+    ) -> usize {
+        assert!(u.n < 2 * b);
+        if !is_root {
+            assert!(u.n >= b - 1);
+        }
+        assert!(u.keys[..u.n].windows(2).all(|w| w[0] < w[1]));
+        if u.is_leaf() {
+            assert!(u.children.iter().all(|c| c.is_none()));
+            assert_eq!(*leaf.get_or_insert(depth), depth);
+            return u.n;
+        }
+        let mut n = u.n;
+        for i in 0..=u.n {
+            let c = u.children[i].as_ref().unwrap();
+            if i > 0 {
+                assert!(u.keys[i - 1] < c.keys[0]);
+            }
+            if i < u.n {
+                assert!(c.keys[c.n - 1] < u.keys[i]);
+            }
+            n += check(c, b, false, depth + 1, leaf);
+        }
+        assert!(u.children[u.n + 1..].iter().all(|c| c.is_none()));
+        n
+    }
+
+    #[test]
+    fn test_sset() {
+        for &b in &[2, 3, 8] {
+            let mut t = BTree::new(b);
+            let mut model = BTreeSet::new();
+            let mut rng = Rng::with_seed(1);
+            for i in 0..3000 {
+                let x = rng.gen_range(2000);
+                if i % 3 == 2 {
+                    assert_eq!(t.remove(&x), model.take(&x));
+                } else {
+                    assert_eq!(t.add(x), model.insert(x));
+                }
+                assert_eq!(check(&t.r, b, true, 0, &mut None), model.len());
+            }
+            assert_eq!(t.size(), model.len());
+            assert!(t.iter().eq(model.iter()));
+            for x in [0, 500, 1999, 2000].iter() {
+                assert_eq!(t.find(x), model.range(x..).next());
+            }
+
+            let all: Vec<_> = model.iter().cloned().collect();
+            for x in &all {
+                assert_eq!(t.remove(x), Some(*x));
+            }
+            assert!(t.is_empty());
+            assert_eq!(t.height(), 0);
+        }
+    }
+
+    #[test]
+    fn test_height() {
+        // 各ノードにB - 1個以上のキーがあるので、高さはlog_B n程度
+        let t: BTree<u32> = (0..100_000).collect();
+        assert!(t.height() <= 4);
+        assert_eq!(t.find(&99_999), Some(&99_999));
+    }
+}
@@ -0,0 +1,771 @@
+use crate::list::lru_cache::LruCache;
+use crate::tree::block_store::BlockStore;
+use std::io;
+
+/// ブロックに決まった大きさで書ける要素
+pub trait Record: Ord + Copy {
+    /// 書くときのバイト数
+    const SIZE: usize;
+    fn encode(&self, buf: &mut [u8]);
+    fn decode(buf: &[u8]) -> Self;
+}
+
+macro_rules! impl_record {
+    ($($t:ty),*) => {
+        $(
+            impl Record for $t {
+                const SIZE: usize = std::mem::size_of::<$t>();
+                fn encode(&self, buf: &mut [u8]) {
+                    buf[..Self::SIZE].copy_from_slice(&self.to_le_bytes());
+                }
+                fn decode(buf: &[u8]) -> Self {
+                    let mut b = [0; std::mem::size_of::<$t>()];
+                    b.copy_from_slice(&buf[..Self::SIZE]);
+                    <$t>::from_le_bytes(b)
+                }
+            }
+        )*
+    };
+}
+
+impl_record!(u16, u32, u64, i16, i32, i64);
+
+fn get_u64(buf: &[u8], at: usize) -> u64 {
+    u64::decode(&buf[at..])
+}
+
+fn put_u64(buf: &mut [u8], at: usize, x: u64) {
+    x.encode(&mut buf[at..]);
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// 外部記憶との間で実際に読み書きしたブロックの数
+/// キャッシュに当たった分は数えない
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IoStats {
+    pub reads: u64,
+    pub writes: u64,
+}
+
+/// data: ブロックの中身
+/// dirty: 読んでから書き換えた
+#[derive(Debug)]
+struct Page {
+    data: Box<[u8]>,
+    dirty: bool,
+}
+
+/// 使ったブロックをLRUでキャッシュし、書き換えたものは追い出すときに書き戻す
+/// 空いたブロックは先頭8バイトに次の空きを書いてリストにする
+///
+/// nblocks: 使ったことのあるブロックの数
+/// free: 空きブロックのリストの先頭
+struct Pager<S> {
+    store: S,
+    cache: LruCache<usize, Page>,
+    stats: IoStats,
+    nblocks: usize,
+    free: usize,
+}
+
+const NIL: usize = usize::MAX;
+
+impl<S: BlockStore> Pager<S> {
+    /// キャッシュに1ページも置けなければInvalidInput
+    fn new(store: S, capacity: usize) -> io::Result<Self> {
+        if capacity == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cache must hold at least one page",
+            ));
+        }
+        Ok(Self {
+            store,
+            cache: LruCache::new(capacity),
+            stats: IoStats::default(),
+            nblocks: 0,
+            free: NIL,
+        })
+    }
+
+    /// 一杯なら一番使っていないページを追い出す
+    fn make_room(&mut self) -> io::Result<()> {
+        if self.cache.size() < self.cache.capacity() {
+            return Ok(());
+        }
+        if let Some((i, page)) = self.cache.pop_lru() {
+            if page.dirty {
+                self.store.write_block(i, &page.data)?;
+                self.stats.writes += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn page(&mut self, i: usize) -> io::Result<&mut Page> {
+        if !self.cache.contains(&i) {
+            self.make_room()?;
+            let mut data = vec![0; self.store.block_size()].into_boxed_slice();
+            self.store.read_block(i, &mut data)?;
+            self.stats.reads += 1;
+            self.cache.put(i, Page { data, dirty: false });
+        }
+        Ok(self.cache.get_mut(&i).unwrap())
+    }
+
+    fn read(&mut self, i: usize) -> io::Result<&[u8]> {
+        Ok(&self.page(i)?.data)
+    }
+
+    /// 読まずに書き換えるので、新しいブロックにも使える
+    fn write(&mut self, i: usize, data: Box<[u8]>) -> io::Result<()> {
+        if let Some(page) = self.cache.get_mut(&i) {
+            *page = Page { data, dirty: true };
+            return Ok(());
+        }
+        self.make_room()?;
+        self.cache.put(i, Page { data, dirty: true });
+        Ok(())
+    }
+
+    /// 空きリストから取るか、末尾に足す
+    fn alloc(&mut self) -> io::Result<usize> {
+        if self.free == NIL {
+            self.nblocks += 1;
+            return Ok(self.nblocks - 1);
+        }
+        let i = self.free;
+        self.free = get_u64(self.read(i)?, 0) as usize;
+        Ok(i)
+    }
+
+    fn free(&mut self, i: usize) -> io::Result<()> {
+        let mut data = vec![0; self.store.block_size()].into_boxed_slice();
+        put_u64(&mut data, 0, self.free as u64);
+        self.write(i, data)?;
+        self.free = i;
+        Ok(())
+    }
+
+    /// 書き換えたページをすべて書き戻す(キャッシュには残す)
+    fn flush(&mut self) -> io::Result<()> {
+        let dirty: Vec<usize> = self
+            .cache
+            .iter()
+            .filter(|(_, p)| p.dirty)
+            .map(|(&i, _)| i)
+            .collect();
+        // 古い順に触れば、キャッシュの中の順番は変わらない
+        for &i in dirty.iter().rev() {
+            let page = self.cache.get_mut(&i).unwrap();
+            self.store.write_block(i, &page.data)?;
+            self.stats.writes += 1;
+            page.dirty = false;
+        }
+        self.store.sync()
+    }
+}
+
+/// ブロックから読み出したノード、葉ならchildrenは空
+#[derive(Debug, Clone)]
+struct Node<T> {
+    keys: Vec<T>,
+    children: Vec<usize>,
+}
+
+impl<T: Record> Node<T> {
+    fn leaf() -> Self {
+        Self {
+            keys: vec![],
+            children: vec![],
+        }
+    }
+
+    fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// [キーの数 u32][葉なら1 u32][キー x (2B - 1)][子 u64 x 2B]
+    /// キーの数が2B - 1を超えていれば、壊れているのでInvalidData
+    fn decode(buf: &[u8], b: usize) -> io::Result<Self> {
+        let n = u32::decode(buf) as usize;
+        if n > 2 * b - 1 {
+            return Err(invalid_data("too many keys in a node"));
+        }
+        let leaf = match u32::decode(&buf[4..]) {
+            0 => false,
+            1 => true,
+            _ => return Err(invalid_data("bad leaf flag in a node")),
+        };
+        let keys = (0..n).map(|i| T::decode(&buf[8 + i * T::SIZE..])).collect();
+        let at = 8 + (2 * b - 1) * T::SIZE;
+        let children = if leaf {
+            vec![]
+        } else {
+            (0..=n).map(|i| get_u64(buf, at + 8 * i) as usize).collect()
+        };
+        Ok(Self { keys, children })
+    }
+
+    fn encode(&self, block_size: usize, b: usize) -> Box<[u8]> {
+        let mut buf = vec![0; block_size].into_boxed_slice();
+        (self.keys.len() as u32).encode(&mut buf);
+        (self.is_leaf() as u32).encode(&mut buf[4..]);
+        for (i, x) in self.keys.iter().enumerate() {
+            x.encode(&mut buf[8 + i * T::SIZE..]);
+        }
+        let at = 8 + (2 * b - 1) * T::SIZE;
+        for (i, &c) in self.children.iter().enumerate() {
+            put_u64(&mut buf, at + 8 * i, c as u64);
+        }
+        buf
+    }
+}
+
+/// ブロックの大きさに入る最大のB
+fn max_b(block_size: usize, key_size: usize) -> usize {
+    (block_size + key_size - 8) / (2 * key_size + 16)
+}
+
+const MAGIC: u64 = 0x4254_5245_4546_494c;
+
+/// 見出しのバイト数(u64 x 8)
+const HEADER_LEN: usize = 64;
+
+enum Added<T> {
+    Exists,
+    Done,
+    Split(T, usize),
+}
+
+/// ノードを1つのブロックに置き、ブロックをファイルなどに持つB木
+/// ブロック0は見出し(MAGIC, ブロックの大きさ, 根, 要素数, ブロック数, 空きリスト, キーのバイト数, B)
+/// 操作はO(log_B n)個のブロックに触れ、キャッシュに当たらなかった分だけ読み書きする
+///
+/// b: ブロックの大きさから決まる(各ノードはB - 1個以上2B - 1個以下のキー)
+/// r: 根のブロック
+/// n: 要素数
+pub struct DiskBTree<T: Record, S: BlockStore> {
+    pager: Pager<S>,
+    b: usize,
+    r: usize,
+    n: usize,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Record, S: BlockStore> DiskBTree<T, S> {
+    /// ブロックの大きさからBを決める
+    /// 見出しかB >= 2のノードが入らなければInvalidInput
+    fn order_for(block_size: usize) -> io::Result<usize> {
+        // max_bが負にならないよう、先に見出しの大きさと比べる
+        if block_size < HEADER_LEN || max_b(block_size, T::SIZE) < 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "block too small",
+            ));
+        }
+        Ok(max_b(block_size, T::SIZE))
+    }
+
+    fn with_pager(pager: Pager<S>) -> io::Result<Self> {
+        let b = Self::order_for(pager.store.block_size())?;
+        Ok(Self {
+            pager,
+            b,
+            r: NIL,
+            n: 0,
+            _marker: std::marker::PhantomData,
+        })
+    }
+
+    /// 空の木を作る、storeの中身は上書きする
+    /// cache_pages: キャッシュに置くブロックの数
+    pub fn create(store: S, cache_pages: usize) -> io::Result<Self> {
+        let mut t = Self::with_pager(Pager::new(store, cache_pages)?)?;
+        t.pager.alloc()?;
+        t.r = t.pager.alloc()?;
+        t.write_node(t.r, &Node::leaf())?;
+        t.flush()?;
+        Ok(t)
+    }
+
+    /// createで作ってflushしたものを開く
+    /// 見出しを確かめてから木を作る(失敗したときにDropで見出しを書かないように)
+    /// ブロックの大きさ、キーのバイト数、Bのどれかが違えばInvalidData
+    pub fn open(store: S, cache_pages: usize) -> io::Result<Self> {
+        let mut pager = Pager::new(store, cache_pages)?;
+        let bs = pager.store.block_size();
+        let b = Self::order_for(bs)?;
+        let h = pager.read(0)?;
+        if get_u64(h, 0) != MAGIC || get_u64(h, 8) as usize != bs {
+            return Err(invalid_data("not a B-tree"));
+        }
+        if get_u64(h, 48) as usize != T::SIZE || get_u64(h, 56) as usize != b {
+            return Err(invalid_data("record size or B does not match"));
+        }
+        let (r, n) = (get_u64(h, 16) as usize, get_u64(h, 24) as usize);
+        let (nblocks, free) = (get_u64(h, 32) as usize, get_u64(h, 40) as usize);
+        if r == 0 || r >= nblocks || (free != NIL && (free == 0 || free >= nblocks)) {
+            return Err(invalid_data("bad B-tree header"));
+        }
+        pager.nblocks = nblocks;
+        pager.free = free;
+        let mut t = Self::with_pager(pager)?;
+        t.r = r;
+        t.n = n;
+        Ok(t)
+    }
+
+    /// 見出しを書いて、書き換えたページをすべて書き戻す
+    pub fn flush(&mut self) -> io::Result<()> {
+        let mut h = vec![0; self.pager.store.block_size()].into_boxed_slice();
+        put_u64(&mut h, 0, MAGIC);
+        put_u64(&mut h, 8, self.pager.store.block_size() as u64);
+        put_u64(&mut h, 16, self.r as u64);
+        put_u64(&mut h, 24, self.n as u64);
+        put_u64(&mut h, 32, self.pager.nblocks as u64);
+        put_u64(&mut h, 40, self.pager.free as u64);
+        put_u64(&mut h, 48, T::SIZE as u64);
+        put_u64(&mut h, 56, self.b as u64);
+        self.pager.write(0, h)?;
+        self.pager.flush()
+    }
+
+    pub fn size(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// B(ブロックの大きさから決まる)、バイト数はBlockStore::block_size
+    pub fn order(&self) -> usize {
+        self.b
+    }
+
+    pub fn stats(&self) -> IoStats {
+        self.pager.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.pager.stats = IoStats::default();
+    }
+
+    /// 根から葉までの辺の数
+    pub fn height(&mut self) -> io::Result<usize> {
+        let mut h = 0;
+        let mut u = self.read_node(self.r)?;
+        while !u.is_leaf() {
+            h += 1;
+            u = self.read_node(u.children[0])?;
+        }
+        Ok(h)
+    }
+
+    /// 子が見出しや使ったことのないブロックを指していれば、壊れているのでInvalidData
+    fn read_node(&mut self, i: usize) -> io::Result<Node<T>> {
+        let u = Node::decode(self.pager.read(i)?, self.b)?;
+        let nblocks = self.pager.nblocks;
+        if u.children.iter().any(|&c| c == 0 || c >= nblocks) {
+            return Err(invalid_data("child out of range"));
+        }
+        Ok(u)
+    }
+
+    fn write_node(&mut self, i: usize, u: &Node<T>) -> io::Result<()> {
+        let data = u.encode(self.pager.store.block_size(), self.b);
+        self.pager.write(i, data)
+    }
+
+    /// x以上で最小の要素
+    pub fn find(&mut self, x: &T) -> io::Result<Option<T>> {
+        let mut u = self.read_node(self.r)?;
+        let mut z = None;
+        loop {
+            let i = match u.keys.binary_search(x) {
+                Ok(i) => return Ok(Some(u.keys[i])),
+                Err(i) => i,
+            };
+            if i < u.keys.len() {
+                z = Some(u.keys[i]);
+            }
+            if u.is_leaf() {
+                return Ok(z);
+            }
+            u = self.read_node(u.children[i])?;
+        }
+    }
+
+    pub fn contains(&mut self, x: &T) -> io::Result<bool> {
+        Ok(self.find(x)? == Some(*x))
+    }
+
+    /// xがすでにあればfalse
+    pub fn add(&mut self, x: T) -> io::Result<bool> {
+        match self.add_rec(self.r, x)? {
+            Added::Exists => return Ok(false),
+            Added::Done => {}
+            Added::Split(y, w) => {
+                let r = Node {
+                    keys: vec![y],
+                    children: vec![self.r, w],
+                };
+                self.r = self.pager.alloc()?;
+                self.write_node(self.r, &r)?;
+            }
+        }
+        self.n += 1;
+        Ok(true)
+    }
+
+    /// 2B個になったら、前半のB個を残して後半のB - 1個を新しいブロックに移す
+    fn add_rec(&mut self, i: usize, x: T) -> io::Result<Added<T>> {
+        let mut u = self.read_node(i)?;
+        let j = match u.keys.binary_search(&x) {
+            Ok(_) => return Ok(Added::Exists),
+            Err(j) => j,
+        };
+        if u.is_leaf() {
+            u.keys.insert(j, x);
+        } else {
+            match self.add_rec(u.children[j], x)? {
+                Added::Split(y, w) => {
+                    u.keys.insert(j, y);
+                    u.children.insert(j + 1, w);
+                }
+                other => return Ok(other),
+            }
+        }
+        if u.keys.len() < 2 * self.b {
+            self.write_node(i, &u)?;
+            return Ok(Added::Done);
+        }
+        let w = Node {
+            keys: u.keys.split_off(self.b + 1),
+            children: if u.is_leaf() {
+                vec![]
+            } else {
+                u.children.split_off(self.b + 1)
+            },
+        };
+        let y = u.keys.pop().unwrap();
+        let wi = self.pager.alloc()?;
+        self.write_node(i, &u)?;
+        self.write_node(wi, &w)?;
+        Ok(Added::Split(y, wi))
+    }
+
+    /// 根のキーがなくなったら、唯一の子を根にする
+    pub fn remove(&mut self, x: &T) -> io::Result<Option<T>> {
+        let y = match self.remove_rec(self.r, x)? {
+            Some(y) => y,
+            None => return Ok(None),
+        };
+        let r = self.read_node(self.r)?;
+        if r.keys.is_empty() && !r.is_leaf() {
+            self.pager.free(self.r)?;
+            self.r = r.children[0];
+        }
+        self.n -= 1;
+        Ok(Some(y))
+    }
+
+    fn remove_rec(&mut self, i: usize, x: &T) -> io::Result<Option<T>> {
+        let mut u = self.read_node(i)?;
+        let y = match u.keys.binary_search(x) {
+            Ok(j) if u.is_leaf() => u.keys.remove(j),
+            Ok(j) => {
+                // 右の部分木の最小と入れ替える
+                let z = self.remove_smallest(u.children[j + 1])?;
+                let y = std::mem::replace(&mut u.keys[j], z);
+                self.check_underflow(&mut u, j + 1)?;
+                y
+            }
+            Err(_) if u.is_leaf() => return Ok(None),
+            Err(j) => match self.remove_rec(u.children[j], x)? {
+                Some(y) => {
+                    self.check_underflow(&mut u, j)?;
+                    y
+                }
+                None => return Ok(None),
+            },
+        };
+        self.write_node(i, &u)?;
+        Ok(Some(y))
+    }
+
+    fn remove_smallest(&mut self, i: usize) -> io::Result<T> {
+        let mut u = self.read_node(i)?;
+        let y = if u.is_leaf() {
+            u.keys.remove(0)
+        } else {
+            let y = self.remove_smallest(u.children[0])?;
+            self.check_underflow(&mut u, 0)?;
+            y
+        };
+        self.write_node(i, &u)?;
+        Ok(y)
+    }
+
+    /// uの子jのキーがB - 1個より少なくなったら、兄弟から借りるか兄弟とまとめる
+    /// uは呼び出し側が書く
+    fn check_underflow(&mut self, u: &mut Node<T>, j: usize) -> io::Result<()> {
+        let b = self.b;
+        // 左に兄弟がいればそれを、いなければ右の兄弟を使う
+        if self.read_node(u.children[j])?.keys.len() >= b - 1 {
+            return Ok(());
+        }
+        let k = if j > 0 { j - 1 } else { j };
+        let (li, ri) = (u.children[k], u.children[k + 1]);
+        let mut left = self.read_node(li)?;
+        let mut right = self.read_node(ri)?;
+
+        if j > 0 && left.keys.len() > b - 1 {
+            let y = left.keys.pop().unwrap();
+            let z = std::mem::replace(&mut u.keys[k], y);
+            right.keys.insert(0, z);
+            if let Some(c) = left.children.pop() {
+                right.children.insert(0, c);
+            }
+        } else if j == 0 && right.keys.len() > b - 1 {
+            let y = right.keys.remove(0);
+            let z = std::mem::replace(&mut u.keys[k], y);
+            left.keys.push(z);
+            if !right.is_leaf() {
+                left.children.push(right.children.remove(0));
+            }
+        } else {
+            // 左 + 親のキー + 右 を左にまとめて、右のブロックを空ける
+            left.keys.push(u.keys.remove(k));
+            u.children.remove(k + 1);
+            left.keys.append(&mut right.keys);
+            left.children.append(&mut right.children);
+            self.write_node(li, &left)?;
+            return self.pager.free(ri);
+        }
+        self.write_node(li, &left)?;
+        self.write_node(ri, &right)
+    }
+
+    /// 昇順
+    pub fn iter(&mut self) -> io::Result<Iter<'_, T, S>> {
+        let mut it = Iter {
+            t: self,
+            stack: vec![],
+        };
+        let r = it.t.r;
+        it.push_left(r)?;
+        Ok(it)
+    }
+}
+
+impl<T: Record, S: BlockStore> Drop for DiskBTree<T, S> {
+    /// BufWriterと同じく、書き戻しの失敗は無視する
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// (ノード, 次に返すキーの位置)を積んでいく中間順のイテレータ
+/// ブロックを読むので要素はio::Result
+pub struct Iter<'a, T: Record, S: BlockStore> {
+    t: &'a mut DiskBTree<T, S>,
+    stack: Vec<(Node<T>, usize)>,
+}
+
+impl<'a, T: Record, S: BlockStore> Iter<'a, T, S> {
+    fn push_left(&mut self, mut i: usize) -> io::Result<()> {
+        loop {
+            let u = self.t.read_node(i)?;
+            let c = u.children.first().cloned();
+            self.stack.push((u, 0));
+            match c {
+                Some(c) => i = c,
+                None => return Ok(()),
+            }
+        }
+    }
+}
+
+impl<'a, T: Record, S: BlockStore> Iterator for Iter<'a, T, S> {
+    type Item = io::Result<T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (u, j) = self.stack.pop()?;
+            if j < u.keys.len() {
+                let x = u.keys[j];
+                let c = u.children.get(j + 1).cloned();
+                self.stack.push((u, j + 1));
+                if let Some(c) = c {
+                    if let Err(e) = self.push_left(c) {
+                        return Some(Err(e));
+                    }
+                }
+                return Some(Ok(x));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_disk_btree {
+    use super::*;
+    use crate::rng::Rng;
+    use crate::tree::block_store::{FileBlockStore, MemBlockStore};
+    use std::collections::BTreeSet;
+
+    /// キーの数、並び、葉の深さがそろっていることを確かめて要素数を返す
+    fn check<T: Record, S: BlockStore>(
+        t: &mut DiskBTree<T, S>,
+        i: usize,
+        depth: usize,
+        leaf: &mut Option<usize>,
+    ) -> usize {
+        let u = t.read_node(i).unwrap();
+        assert!(u.keys.len() < 2 * t.b);
+        if i != t.r {
+            assert!(u.keys.len() >= t.b - 1);
+        }
+        assert!(u.keys.windows(2).all(|w| w[0] < w[1]));
+        if u.is_leaf() {
+            assert_eq!(*leaf.get_or_insert(depth), depth);
+            return u.keys.len();
+        }
+        assert_eq!(u.children.len(), u.keys.len() + 1);
+        let mut n = u.keys.len();
+        for &c in &u.children {
+            n += check(t, c, depth + 1, leaf);
+        }
+        n
+    }
+
+    #[test]
+    fn test_sset() {
+        let mut store = MemBlockStore::new(128);
+        let mut model = BTreeSet::new();
+        {
+            let mut t: DiskBTree<u32, _> = DiskBTree::create(&mut store, 4).unwrap();
+            assert_eq!(t.order(), 5);
+            let mut rng = Rng::with_seed(1);
+            for i in 0..3000 {
+                let x = rng.gen_range(1000) as u32;
+                if i % 3 == 2 {
+                    assert_eq!(t.remove(&x).unwrap(), model.take(&x));
+                } else {
+                    assert_eq!(t.add(x).unwrap(), model.insert(x));
+                }
+                if i % 100 == 0 {
+                    let r = t.r;
+                    assert_eq!(check(&mut t, r, 0, &mut None), model.len());
+                }
+            }
+            for x in [0, 500, 999, 1000].iter() {
+                assert_eq!(t.find(x).unwrap(), model.range(x..).next().cloned());
+            }
+            assert_eq!(t.size(), model.len());
+        }
+        // 閉じたときに書き戻したものを開き直す
+        let blocks = store.len();
+        let mut t: DiskBTree<u32, _> = DiskBTree::open(&mut store, 4).unwrap();
+        let all: Vec<u32> = t.iter().unwrap().map(|x| x.unwrap()).collect();
+        assert!(all.iter().eq(model.iter()));
+        for x in &all {
+            assert_eq!(t.remove(x).unwrap(), Some(*x));
+        }
+        assert!(t.is_empty());
+        assert_eq!(t.height().unwrap(), 0);
+        // 空いたブロックを使い回すので、ファイルは伸びない
+        let nblocks = t.pager.nblocks;
+        for x in &all[..all.len() / 2] {
+            t.add(*x).unwrap();
+        }
+        assert_eq!(t.pager.nblocks, nblocks);
+        drop(t);
+        assert_eq!(store.len(), blocks);
+
+        assert!(DiskBTree::<u32, _>::open(MemBlockStore::new(128), 4).is_err());
+    }
+
+    #[test]
+    fn test_io_bounds() {
+        // B = 8、キャッシュは1ページだけ
+        let path = std::env::temp_dir().join(format!("disk_btree_{}", std::process::id()));
+        let store = FileBlockStore::open(&path, 256).unwrap();
+        let mut t: DiskBTree<u64, _> = DiskBTree::create(store, 1).unwrap();
+        for x in 0..20_000 {
+            t.add(x * 7 % 20_000).unwrap();
+        }
+        let h = t.height().unwrap() as u64;
+        // 高さはlog_B n程度
+        assert!(h <= 5);
+
+        for x in (0..20_000).step_by(997) {
+            t.flush().unwrap();
+            t.reset_stats();
+            assert_eq!(t.find(&x).unwrap(), Some(x));
+            // 根から葉まで1ブロックずつ読むだけ
+            let st = t.stats();
+            assert!(st.reads <= h + 1);
+            assert_eq!(st.writes, 0);
+
+            t.reset_stats();
+            t.remove(&x).unwrap();
+            t.add(x).unwrap();
+            let st = t.stats();
+            assert!(st.reads + st.writes <= 8 * (h + 1));
+        }
+        drop(t);
+
+        let store = FileBlockStore::open(&path, 256).unwrap();
+        let mut t: DiskBTree<u64, _> = DiskBTree::open(store, 16).unwrap();
+        assert_eq!(t.size(), 20_000);
+        assert_eq!(t.find(&19_999).unwrap(), Some(19_999));
+        assert_eq!(t.find(&20_000).unwrap(), None);
+        drop(t);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_bad_blocks() {
+        // 見出しもB = 2のノードも入らない
+        let e = DiskBTree::<u64, _>::create(MemBlockStore::new(4), 4)
+            .err()
+            .unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        let e = DiskBTree::<u64, _>::open(MemBlockStore::new(32), 4)
+            .err()
+            .unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        // キャッシュに1ページも置けない
+        let e = DiskBTree::<u64, _>::create(MemBlockStore::new(128), 0)
+            .err()
+            .unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+
+        let mut store = MemBlockStore::new(128);
+        {
+            let mut t: DiskBTree<u32, _> = DiskBTree::create(&mut store, 4).unwrap();
+            for x in 0..100 {
+                t.add(x).unwrap();
+            }
+        }
+        // キーのバイト数もBも違う
+        let e = DiskBTree::<u64, _>::open(&mut store, 4).err().unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+        // 根のキーの数を2B - 1より大きくする
+        let mut buf = vec![0; 128];
+        store.read_block(0, &mut buf).unwrap();
+        let r = get_u64(&buf, 16) as usize;
+        store.read_block(r, &mut buf).unwrap();
+        1000u32.encode(&mut buf);
+        store.write_block(r, &buf).unwrap();
+        let mut t: DiskBTree<u32, _> = DiskBTree::open(&mut store, 4).unwrap();
+        let e = t.find(&5).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,92 @@
+use crate::array::allocate_heap;
+use crate::range::to_range;
+use std::ops::{Add, RangeBounds, Sub};
+
+/// 先頭からの和をO(log n)で求め、1点への加算もO(log n)でできる木(Binary Indexed Tree)
+/// a[i]は(i - (iの最下位ビット), i]の和(1から数える)
+#[derive(Debug)]
+pub struct FenwickTree<T> {
+    a: Box<[T]>,
+}
+
+impl<T> FenwickTree<T>
+where
+    T: Default + Copy + Add<Output = T> + Sub<Output = T>,
+{
+    pub fn new(n: usize) -> Self {
+        Self {
+            a: allocate_heap(n + 1),
+        }
+    }
+
+    /// 自分の区間の和を、自分を含む次の区間に足していけばO(n)で作れる
+    pub fn from_slice(xs: &[T]) -> Self {
+        let mut t = Self::new(xs.len());
+        let n = xs.len();
+        for i in 1..=n {
+            t.a[i] = t.a[i] + xs[i - 1];
+            let j = i + (i & i.wrapping_neg());
+            if j <= n {
+                t.a[j] = t.a[j] + t.a[i];
+            }
+        }
+        t
+    }
+
+    pub fn size(&self) -> usize {
+        self.a.len() - 1
+    }
+
+    /// i番目(0から)にxを足す
+    pub fn add(&mut self, i: usize, x: T) {
+        assert!(i < self.size(), "index out of bounds");
+        let mut i = i + 1;
+        while i < self.a.len() {
+            self.a[i] = self.a[i] + x;
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    /// 先頭からi個の和
+    pub fn prefix_sum(&self, i: usize) -> T {
+        assert!(i <= self.size(), "index out of bounds");
+        let mut i = i;
+        let mut s = T::default();
+        while i > 0 {
+            s = s + self.a[i];
+            i -= i & i.wrapping_neg();
+        }
+        s
+    }
+
+    pub fn range_sum<R: RangeBounds<usize>>(&self, range: R) -> T {
+        let (lo, hi) = to_range(range, self.size());
+        self.prefix_sum(hi) - self.prefix_sum(lo)
+    }
+}
+
+#[cfg(test)]
+mod test_fenwick {
+    use super::*;
+
+    #[test]
+    fn test_prefix_sum() {
+        let xs: Vec<i64> = (0..77).map(|i| (i * 13 % 17) - 8).collect();
+        let mut t = FenwickTree::from_slice(&xs);
+        let mut u = FenwickTree::new(xs.len());
+        for (i, &x) in xs.iter().enumerate() {
+            u.add(i, x);
+        }
+        for i in 0..=xs.len() {
+            let s: i64 = xs[..i].iter().sum();
+            assert_eq!(t.prefix_sum(i), s);
+            assert_eq!(u.prefix_sum(i), s);
+        }
+
+        t.add(30, 100);
+        assert_eq!(t.range_sum(30..31), xs[30] + 100);
+        assert_eq!(t.range_sum(20..=40), xs[20..=40].iter().sum::<i64>() + 100);
+        assert_eq!(t.range_sum(..), xs.iter().sum::<i64>() + 100);
+        assert_eq!(t.range_sum(5..5), 0);
+    }
+}
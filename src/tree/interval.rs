@@ -0,0 +1,273 @@
+use crate::rng::Rng;
+use crate::tree::node::{BinaryNode, InOrder};
+use crate::tree::treap_core::{self, TreapNode};
+use std::cmp::Ordering;
+
+type Tree<K, V> = Option<Box<Node<K, V>>>;
+
+/// [lo, hi]の閉区間と値
+/// p: 優先度(小さいほど根に近い)
+/// max: 部分木の区間の右端の最大値
+#[derive(Debug)]
+struct Node<K, V> {
+    lo: K,
+    hi: K,
+    v: V,
+    p: u64,
+    max: K,
+    left: Tree<K, V>,
+    right: Tree<K, V>,
+}
+
+impl<K: Ord, V> Node<K, V> {
+    /// 左端、右端の順に比べる
+    fn cmp_key(&self, lo: &K, hi: &K) -> Ordering {
+        lo.cmp(&self.lo).then_with(|| hi.cmp(&self.hi))
+    }
+}
+
+impl<K: Ord + Clone, V> BinaryNode for Node<K, V> {
+    fn left(&self) -> &Tree<K, V> {
+        &self.left
+    }
+
+    fn right(&self) -> &Tree<K, V> {
+        &self.right
+    }
+
+    fn left_mut(&mut self) -> &mut Tree<K, V> {
+        &mut self.left
+    }
+
+    fn right_mut(&mut self) -> &mut Tree<K, V> {
+        &mut self.right
+    }
+
+    /// 部分木の右端の最大値を計算し直す
+    fn update(&mut self) {
+        let mut m = &self.hi;
+        for c in self.left.iter().chain(self.right.iter()) {
+            if c.max > *m {
+                m = &c.max;
+            }
+        }
+        self.max = m.clone();
+    }
+}
+
+impl<K: Ord + Clone, V> TreapNode for Node<K, V> {
+    fn p(&self) -> u64 {
+        self.p
+    }
+}
+
+fn remove<K: Ord + Clone, V>(u: &mut Tree<K, V>, lo: &K, hi: &K) -> Option<V> {
+    let node = u.as_mut()?;
+    let removed = match node.cmp_key(lo, hi) {
+        Ordering::Less => remove(&mut node.left, lo, hi),
+        Ordering::Greater => remove(&mut node.right, lo, hi),
+        Ordering::Equal => return Some(treap_core::trickle_down(u).v),
+    };
+    if removed.is_some() {
+        node.update();
+    }
+    removed
+}
+
+/// 左端でならべたTreapの各ノードに、部分木の右端の最大値を持たせた区間木
+/// 部分木の最大値が問い合わせの左端より小さければ、その部分木には重なる区間がない
+/// r: 根
+/// n: 区間の数
+/// rng: 優先度を作る
+#[derive(Debug)]
+pub struct IntervalTree<K, V> {
+    r: Tree<K, V>,
+    n: usize,
+    rng: Rng,
+}
+
+impl<K, V> IntervalTree<K, V> {
+    pub fn new() -> Self {
+        Self::with_rng(Rng::new())
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_rng(Rng::with_seed(seed))
+    }
+
+    fn with_rng(rng: Rng) -> Self {
+        Self { r: None, n: 0, rng }
+    }
+
+    pub fn size(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+}
+
+impl<K: Ord + Clone, V> IntervalTree<K, V> {
+    /// 閉区間[lo, hi]を入れる
+    pub fn insert(&mut self, lo: K, hi: K, v: V) {
+        assert!(lo <= hi, "interval must satisfy lo <= hi");
+        let w = Box::new(Node {
+            max: hi.clone(),
+            lo,
+            hi,
+            v,
+            p: self.rng.next_u64(),
+            left: None,
+            right: None,
+        });
+        // 同じ区間は右に入れるので、重複してもよい
+        treap_core::add(&mut self.r, w, &|u: &Node<K, V>, w: &Node<K, V>| {
+            u.cmp_key(&w.lo, &w.hi) == Ordering::Less
+        });
+        self.n += 1;
+    }
+
+    /// [lo, hi]と等しい区間を1つ取り除いてその値を返す
+    pub fn remove(&mut self, lo: &K, hi: &K) -> Option<V> {
+        let v = remove(&mut self.r, lo, hi)?;
+        self.n -= 1;
+        Some(v)
+    }
+
+    /// [lo, hi]と重なる区間を左端の順に
+    pub fn overlaps<'a>(&'a self, lo: &'a K, hi: &'a K) -> Overlaps<'a, K, V> {
+        let mut it = Overlaps {
+            stack: vec![],
+            lo,
+            hi,
+        };
+        it.push_left(&self.r);
+        it
+    }
+
+    /// xを含む区間を左端の順に
+    pub fn stab<'a>(&'a self, x: &'a K) -> Overlaps<'a, K, V> {
+        self.overlaps(x, x)
+    }
+
+    /// すべての区間を左端の順に
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &K, &V)> {
+        InOrder::new(self.r.as_deref()).map(|u| (&u.lo, &u.hi, &u.v))
+    }
+}
+
+impl<K, V> Default for IntervalTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 重なる区間を中間順にたどるイテレータ
+/// 右端の最大値がloより小さい部分木と、左端がhiより大きい部分木には下りない
+pub struct Overlaps<'a, K, V> {
+    stack: Vec<&'a Node<K, V>>,
+    lo: &'a K,
+    hi: &'a K,
+}
+
+impl<'a, K: Ord, V> Overlaps<'a, K, V> {
+    fn push_left(&mut self, mut w: &'a Tree<K, V>) {
+        while let Some(u) = w {
+            if u.max < *self.lo {
+                break;
+            }
+            self.stack.push(u);
+            w = &u.left;
+        }
+    }
+}
+
+impl<'a, K: Ord, V> Iterator for Overlaps<'a, K, V> {
+    type Item = (&'a K, &'a K, &'a V);
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(u) = self.stack.pop() {
+            if u.lo > *self.hi {
+                // 残りはすべて左端がさらに大きい
+                self.stack.clear();
+                return None;
+            }
+            self.push_left(&u.right);
+            if *self.lo <= u.hi {
+                return Some((&u.lo, &u.hi, &u.v));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test_interval {
+    use super::*;
+
+    fn check<K: Ord + Clone, V>(u: &Tree<K, V>) {
+        if let Some(node) = u {
+            let mut m = node.hi.clone();
+            for c in node.left.iter().chain(node.right.iter()) {
+                assert!(node.p <= c.p);
+                if c.max > m {
+                    m = c.max.clone();
+                }
+            }
+            assert!(m == node.max);
+            check(&node.left);
+            check(&node.right);
+        }
+    }
+
+    #[test]
+    fn test_queries() {
+        let mut t = IntervalTree::with_seed(1);
+        let mut v = vec![];
+        let mut rng = Rng::with_seed(2);
+        for i in 0..500 {
+            let lo = rng.gen_range(1000) as u64;
+            let hi = lo + rng.gen_range(37) as u64;
+            t.insert(lo, hi, i);
+            v.push((lo, hi, i));
+        }
+        // 同じ区間を重ねて入れてもよい
+        t.insert(10, 20, 500);
+        t.insert(10, 20, 501);
+        v.push((10, 20, 500));
+        v.push((10, 20, 501));
+        check(&t.r);
+        assert_eq!(t.size(), 502);
+
+        for &(a, b) in &[(0, 0), (15, 15), (100, 130), (990, 2000), (500, 400)] {
+            let mut got: Vec<_> = t.overlaps(&a, &b).map(|(l, h, x)| (*l, *h, *x)).collect();
+            // 左端の順に出てくる
+            assert!(got.windows(2).all(|w| w[0].0 <= w[1].0));
+            got.sort();
+            let mut expected: Vec<_> = v
+                .iter()
+                .filter(|&&(l, h, _)| l <= b && a <= h)
+                .cloned()
+                .collect();
+            expected.sort();
+            assert_eq!(got, expected);
+        }
+        assert!(t.stab(&15).any(|(_, _, &x)| x == 501));
+        assert_eq!(t.stab(&2000).next(), None);
+
+        assert!(t.remove(&10, &20).is_some());
+        assert!(t.remove(&10, &20).is_some());
+        assert_eq!(t.remove(&10, &21), None);
+        for &(l, h, _) in v.iter().take(250) {
+            assert!(t.remove(&l, &h).is_some());
+            check(&t.r);
+        }
+        assert_eq!(t.size(), 250);
+        assert!(t
+            .iter()
+            .map(|(l, _, _)| l)
+            .collect::<Vec<_>>()
+            .windows(2)
+            .all(|w| w[0] <= w[1]));
+    }
+}
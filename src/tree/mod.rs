@@ -0,0 +1,26 @@
+pub mod avl;
+pub mod binary_tree;
+pub mod block_store;
+pub mod bst;
+pub mod btree;
+pub mod disk_btree;
+pub mod fenwick;
+pub mod interval;
+pub(crate) mod node;
+pub mod redblack;
+pub mod scapegoat;
+pub mod segment_tree;
+pub mod splay;
+pub mod treap;
+pub(crate) mod treap_core;
+
+/// 本のSSetインターフェース: 順序のついた集合
+pub trait SSet<T> {
+    /// 要素数
+    fn size(&self) -> usize;
+    /// xがすでにあればfalse
+    fn add(&mut self, x: T) -> bool;
+    fn remove(&mut self, x: &T) -> Option<T>;
+    /// x以上で最小の要素
+    fn find(&self, x: &T) -> Option<&T>;
+}
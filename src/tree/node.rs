@@ -0,0 +1,81 @@
+/// 子をたどれるノードへの参照(&Box<Node>や、配列に置いた木の添字など)
+pub(crate) trait NodeRef: Copy {
+    fn left(self) -> Option<Self>;
+    fn right(self) -> Option<Self>;
+}
+
+/// 左の子をスタックに積んでいく中間順のイテレータ
+#[derive(Debug, Clone)]
+pub(crate) struct InOrder<N> {
+    stack: Vec<N>,
+}
+
+impl<N: NodeRef> InOrder<N> {
+    pub(crate) fn new(r: Option<N>) -> Self {
+        let mut it = Self { stack: vec![] };
+        it.push_left(r);
+        it
+    }
+
+    fn push_left(&mut self, mut u: Option<N>) {
+        while let Some(w) = u {
+            self.stack.push(w);
+            u = w.left();
+        }
+    }
+}
+
+impl<N: NodeRef> Iterator for InOrder<N> {
+    type Item = N;
+    fn next(&mut self) -> Option<Self::Item> {
+        let u = self.stack.pop()?;
+        self.push_left(u.right());
+        Some(u)
+    }
+}
+
+/// 子をBoxで持つ二分木のノード
+pub(crate) trait BinaryNode: Sized {
+    fn left(&self) -> &Option<Box<Self>>;
+    fn right(&self) -> &Option<Box<Self>>;
+    fn left_mut(&mut self) -> &mut Option<Box<Self>>;
+    fn right_mut(&mut self) -> &mut Option<Box<Self>>;
+    /// 子が変わった後に部分木の情報を計算し直す
+    fn update(&mut self);
+}
+
+impl<'a, N: BinaryNode> NodeRef for &'a N {
+    fn left(self) -> Option<Self> {
+        BinaryNode::left(self).as_deref()
+    }
+
+    fn right(self) -> Option<Self> {
+        BinaryNode::right(self).as_deref()
+    }
+}
+
+/// 右の子を上げる
+pub(crate) fn rotate_left<N: BinaryNode>(u: &mut Box<N>) {
+    let mut w = u
+        .right_mut()
+        .take()
+        .expect("rotate_left needs a right child");
+    *u.right_mut() = w.left_mut().take();
+    u.update();
+    std::mem::swap(u, &mut w);
+    *u.left_mut() = Some(w);
+    u.update();
+}
+
+/// 左の子を上げる
+pub(crate) fn rotate_right<N: BinaryNode>(u: &mut Box<N>) {
+    let mut w = u
+        .left_mut()
+        .take()
+        .expect("rotate_right needs a left child");
+    *u.left_mut() = w.right_mut().take();
+    u.update();
+    std::mem::swap(u, &mut w);
+    *u.right_mut() = Some(w);
+    u.update();
+}
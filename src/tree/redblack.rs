@@ -0,0 +1,699 @@
+use crate::tree::node::{InOrder, NodeRef};
+use crate::tree::SSet;
+use std::cmp::Ordering;
+use std::iter::FromIterator;
+
+/// 番兵のnilの位置、nilは黒で、削除の途中だけ二重の黒(2)になりうる
+const NIL: usize = 0;
+const RED: u8 = 0;
+const BLACK: u8 = 1;
+
+/// entry: nilだけNone
+/// colour: 赤0、黒1(削除の途中は2になりうる)
+#[derive(Debug, Clone)]
+struct Node<K, V> {
+    entry: Option<(K, V)>,
+    colour: u8,
+    parent: usize,
+    left: usize,
+    right: usize,
+}
+
+impl<K, V> Node<K, V> {
+    fn nil() -> Self {
+        Self {
+            entry: None,
+            colour: BLACK,
+            parent: NIL,
+            left: NIL,
+            right: NIL,
+        }
+    }
+}
+
+/// 本の左傾赤黒木による順序つきマップ
+/// 赤が続かず、根から葉までの黒の数が等しく、左の子が黒なら右の子も黒
+/// 本と同じく親へのポインタと番兵のnilを使い、ノードは配列に置いて位置で指す
+///
+/// u: ノードの配列、u[NIL]が番兵
+/// free: 空いている位置
+/// r: 根
+/// n: 要素数
+#[derive(Debug, Clone)]
+pub struct RedBlackMap<K, V> {
+    u: Vec<Node<K, V>>,
+    free: Vec<usize>,
+    r: usize,
+    n: usize,
+}
+
+impl<K, V> RedBlackMap<K, V> {
+    pub fn new() -> Self {
+        Self {
+            u: vec![Node::nil()],
+            free: vec![],
+            r: NIL,
+            n: 0,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    pub fn height(&self) -> isize {
+        self.height_of(self.r)
+    }
+
+    fn height_of(&self, u: usize) -> isize {
+        if u == NIL {
+            return -1;
+        }
+        let (l, r) = (self.u[u].left, self.u[u].right);
+        1 + std::cmp::max(self.height_of(l), self.height_of(r))
+    }
+
+    /// キーの昇順
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            nodes: InOrder::new(Slot::new(self, self.r)),
+        }
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys { iter: self.iter() }
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, v)| v)
+    }
+
+    fn entry(&self, u: usize) -> &(K, V) {
+        self.u[u].entry.as_ref().unwrap()
+    }
+
+    fn colour(&self, u: usize) -> u8 {
+        self.u[u].colour
+    }
+
+    fn left(&self, u: usize) -> usize {
+        self.u[u].left
+    }
+
+    fn right(&self, u: usize) -> usize {
+        self.u[u].right
+    }
+
+    fn parent(&self, u: usize) -> usize {
+        self.u[u].parent
+    }
+
+    fn new_node(&mut self, k: K, v: V) -> usize {
+        let node = Node {
+            entry: Some((k, v)),
+            colour: RED,
+            parent: NIL,
+            left: NIL,
+            right: NIL,
+        };
+        match self.free.pop() {
+            Some(i) => {
+                self.u[i] = node;
+                i
+            }
+            None => {
+                self.u.push(node);
+                self.u.len() - 1
+            }
+        }
+    }
+
+    /// 親のuへの辺をwに付け替える
+    fn replace_child(&mut self, p: usize, u: usize, w: usize) {
+        if p == NIL {
+            self.r = w;
+        } else if self.u[p].left == u {
+            self.u[p].left = w;
+        } else {
+            self.u[p].right = w;
+        }
+    }
+
+    /// 右の子を上げる
+    fn rotate_left(&mut self, u: usize) {
+        let w = self.right(u);
+        let p = self.parent(u);
+        self.u[w].parent = p;
+        self.replace_child(p, u, w);
+        let wl = self.left(w);
+        self.u[u].right = wl;
+        if wl != NIL {
+            self.u[wl].parent = u;
+        }
+        self.u[u].parent = w;
+        self.u[w].left = u;
+    }
+
+    /// 左の子を上げる
+    fn rotate_right(&mut self, u: usize) {
+        let w = self.left(u);
+        let p = self.parent(u);
+        self.u[w].parent = p;
+        self.replace_child(p, u, w);
+        let wr = self.right(w);
+        self.u[u].left = wr;
+        if wr != NIL {
+            self.u[wr].parent = u;
+        }
+        self.u[u].parent = w;
+        self.u[w].right = u;
+    }
+
+    /// uの黒を子に押し下げる
+    fn push_black(&mut self, u: usize) {
+        let (l, r) = (self.left(u), self.right(u));
+        self.u[u].colour -= 1;
+        self.u[l].colour += 1;
+        self.u[r].colour += 1;
+    }
+
+    /// 子の黒をuに引き上げる
+    fn pull_black(&mut self, u: usize) {
+        let (l, r) = (self.left(u), self.right(u));
+        self.u[u].colour += 1;
+        self.u[l].colour -= 1;
+        self.u[r].colour -= 1;
+    }
+
+    fn swap_colours(&mut self, u: usize, w: usize) {
+        let c = self.u[u].colour;
+        self.u[u].colour = self.u[w].colour;
+        self.u[w].colour = c;
+    }
+
+    /// uと右の子の色を入れ替えてから左に回す
+    fn flip_left(&mut self, u: usize) {
+        self.swap_colours(u, self.right(u));
+        self.rotate_left(u);
+    }
+
+    /// uと左の子の色を入れ替えてから右に回す
+    fn flip_right(&mut self, u: usize) {
+        self.swap_colours(u, self.left(u));
+        self.rotate_right(u);
+    }
+
+    /// 赤い葉uを入れた後、赤が続くところや右に傾いたところを根に向かって直す
+    fn add_fixup(&mut self, mut u: usize) {
+        while self.colour(u) == RED {
+            if u == self.r {
+                self.u[u].colour = BLACK;
+                return;
+            }
+            let mut w = self.parent(u);
+            if self.colour(self.left(w)) == BLACK {
+                self.flip_left(w);
+                u = w;
+                w = self.parent(u);
+            }
+            if self.colour(w) == BLACK {
+                // 赤が続いていない
+                return;
+            }
+            let g = self.parent(w);
+            if self.colour(self.right(g)) == BLACK {
+                self.flip_right(g);
+                return;
+            }
+            self.push_black(g);
+            u = g;
+        }
+    }
+
+    /// 二重の黒になったuから、根に向かって黒の数をそろえる
+    fn remove_fixup(&mut self, mut u: usize) {
+        while self.colour(u) > BLACK {
+            if u == self.r {
+                self.u[u].colour = BLACK;
+            } else if self.colour(self.left(self.parent(u))) == RED {
+                u = self.remove_fixup_case1(u);
+            } else if u == self.left(self.parent(u)) {
+                u = self.remove_fixup_case2(u);
+            } else {
+                u = self.remove_fixup_case3(u);
+            }
+        }
+        if u != self.r {
+            // 左傾を戻す
+            let w = self.parent(u);
+            if self.colour(self.right(w)) == RED && self.colour(self.left(w)) == BLACK {
+                self.flip_left(w);
+            }
+        }
+    }
+
+    /// uの兄弟が赤(左の子)のとき
+    fn remove_fixup_case1(&mut self, u: usize) -> usize {
+        self.flip_right(self.parent(u));
+        u
+    }
+
+    /// uが左の子で、兄弟が黒のとき
+    fn remove_fixup_case2(&mut self, u: usize) -> usize {
+        let w = self.parent(u);
+        let v = self.right(w);
+        self.pull_black(w);
+        self.flip_left(w);
+        let q = self.right(w);
+        if self.colour(q) == RED {
+            // qとwで赤が続いている
+            self.rotate_left(w);
+            self.flip_right(v);
+            self.push_black(q);
+            if self.colour(self.right(v)) == RED {
+                self.flip_left(v);
+            }
+            q
+        } else {
+            v
+        }
+    }
+
+    /// uが右の子で、兄弟が黒のとき
+    fn remove_fixup_case3(&mut self, u: usize) -> usize {
+        let w = self.parent(u);
+        let v = self.left(w);
+        self.pull_black(w);
+        self.flip_right(w);
+        let q = self.left(w);
+        if self.colour(q) == RED {
+            // qとwで赤が続いている
+            self.rotate_right(w);
+            self.flip_left(v);
+            self.push_black(q);
+            q
+        } else if self.colour(self.left(v)) == RED {
+            // vの子が両方赤
+            self.push_black(v);
+            v
+        } else {
+            // 左傾を戻す
+            self.flip_left(v);
+            w
+        }
+    }
+
+    /// 子が1つ以下のuを外し、その子を親につなぐ
+    fn splice(&mut self, u: usize) {
+        let s = if self.left(u) != NIL {
+            self.left(u)
+        } else {
+            self.right(u)
+        };
+        let p = self.parent(u);
+        self.replace_child(p, u, s);
+        if s != NIL {
+            self.u[s].parent = p;
+        }
+    }
+}
+
+impl<K: Ord, V> RedBlackMap<K, V> {
+    /// kを探して最後に見たノード(kがあればそのノード、空ならNIL)
+    fn find_last(&self, k: &K) -> usize {
+        let mut w = self.r;
+        let mut prev = NIL;
+        while w != NIL {
+            prev = w;
+            match k.cmp(&self.entry(w).0) {
+                Ordering::Less => w = self.left(w),
+                Ordering::Greater => w = self.right(w),
+                Ordering::Equal => return w,
+            }
+        }
+        prev
+    }
+
+    fn find_node(&self, k: &K) -> Option<usize> {
+        let u = self.find_last(k);
+        if u != NIL && self.entry(u).0 == *k {
+            Some(u)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_key_value(&self, k: &K) -> Option<(&K, &V)> {
+        self.find_node(k).map(|u| {
+            let (k, v) = self.entry(u);
+            (k, v)
+        })
+    }
+
+    pub fn get(&self, k: &K) -> Option<&V> {
+        self.get_key_value(k).map(|(_, v)| v)
+    }
+
+    pub fn get_mut(&mut self, k: &K) -> Option<&mut V> {
+        let u = self.find_node(k)?;
+        self.u[u].entry.as_mut().map(|(_, v)| v)
+    }
+
+    pub fn contains_key(&self, k: &K) -> bool {
+        self.find_node(k).is_some()
+    }
+
+    /// k以上で最小のキーの要素
+    pub fn find(&self, k: &K) -> Option<(&K, &V)> {
+        let mut w = self.r;
+        let mut z = None;
+        while w != NIL {
+            let (wk, wv) = self.entry(w);
+            match k.cmp(wk) {
+                Ordering::Less => {
+                    z = Some((wk, wv));
+                    w = self.left(w);
+                }
+                Ordering::Greater => w = self.right(w),
+                Ordering::Equal => return Some((wk, wv)),
+            }
+        }
+        z
+    }
+
+    /// すでにkがあれば値を置き換えて古い値を返す
+    /// なければ赤い葉として入れてからadd_fixupで直す
+    pub fn insert(&mut self, k: K, v: V) -> Option<V> {
+        let p = self.find_last(&k);
+        let dir = if p == NIL {
+            Ordering::Equal
+        } else {
+            k.cmp(&self.entry(p).0)
+        };
+        if p != NIL && dir == Ordering::Equal {
+            let e = self.u[p].entry.as_mut().unwrap();
+            return Some(std::mem::replace(&mut e.1, v));
+        }
+
+        let u = self.new_node(k, v);
+        self.u[u].parent = p;
+        if p == NIL {
+            self.r = u;
+        } else if dir == Ordering::Less {
+            self.u[p].left = u;
+        } else {
+            self.u[p].right = u;
+        }
+        self.n += 1;
+        self.add_fixup(u);
+        None
+    }
+
+    /// 子が1つ以下のノードwを外し、wの色をその子uに足してremove_fixupで直す
+    /// 子が2つあるときは右の部分木の最小のノードと中身を入れ替えてそちらを外す
+    pub fn remove_entry(&mut self, k: &K) -> Option<(K, V)> {
+        let mut u = self.find_node(k)?;
+        let mut w = self.right(u);
+        if w == NIL {
+            w = u;
+            u = self.left(w);
+        } else {
+            while self.left(w) != NIL {
+                w = self.left(w);
+            }
+            let e = self.u[w].entry.take();
+            let old = std::mem::replace(&mut self.u[u].entry, e);
+            self.u[w].entry = old;
+            u = self.right(w);
+        }
+        self.splice(w);
+        self.u[u].colour += self.u[w].colour;
+        self.u[u].parent = self.parent(w);
+        self.remove_fixup(u);
+        self.u[NIL] = Node::nil();
+
+        let removed = std::mem::replace(&mut self.u[w], Node::nil()).entry;
+        self.free.push(w);
+        self.n -= 1;
+        removed
+    }
+
+    pub fn remove(&mut self, k: &K) -> Option<V> {
+        self.remove_entry(k).map(|(_, v)| v)
+    }
+}
+
+impl<K, V> Default for RedBlackMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for RedBlackMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut m = Self::new();
+        for (k, v) in iter {
+            m.insert(k, v);
+        }
+        m
+    }
+}
+
+/// 配列に置いたノードuへの参照(nilならNone)
+#[derive(Debug)]
+struct Slot<'a, K, V> {
+    map: &'a RedBlackMap<K, V>,
+    u: usize,
+}
+
+impl<'a, K, V> Slot<'a, K, V> {
+    fn new(map: &'a RedBlackMap<K, V>, u: usize) -> Option<Self> {
+        if u == NIL {
+            None
+        } else {
+            Some(Self { map, u })
+        }
+    }
+}
+
+impl<'a, K, V> Clone for Slot<'a, K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, K, V> Copy for Slot<'a, K, V> {}
+
+impl<'a, K, V> NodeRef for Slot<'a, K, V> {
+    fn left(self) -> Option<Self> {
+        Self::new(self.map, self.map.left(self.u))
+    }
+
+    fn right(self) -> Option<Self> {
+        Self::new(self.map, self.map.right(self.u))
+    }
+}
+
+/// 中間順のイテレータ
+#[derive(Debug, Clone)]
+pub struct Iter<'a, K, V> {
+    nodes: InOrder<Slot<'a, K, V>>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+    fn next(&mut self) -> Option<Self::Item> {
+        let Slot { map, u } = self.nodes.next()?;
+        let (k, v) = map.entry(u);
+        Some((k, v))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Keys<'a, K, V> {
+    iter: Iter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for Keys<'a, K, V> {
+    type Item = &'a K;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(k, _)| k)
+    }
+}
+
+/// RedBlackMapのキーだけを使う集合
+#[derive(Debug, Clone)]
+pub struct RedBlackTree<T> {
+    map: RedBlackMap<T, ()>,
+}
+
+impl<T> RedBlackTree<T> {
+    pub fn new() -> Self {
+        Self {
+            map: RedBlackMap::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn height(&self) -> isize {
+        self.map.height()
+    }
+
+    /// 昇順
+    pub fn iter(&self) -> Keys<'_, T, ()> {
+        self.map.keys()
+    }
+}
+
+impl<T: Ord> SSet<T> for RedBlackTree<T> {
+    fn size(&self) -> usize {
+        self.map.size()
+    }
+
+    fn add(&mut self, x: T) -> bool {
+        if self.map.contains_key(&x) {
+            return false;
+        }
+        self.map.insert(x, ());
+        true
+    }
+
+    fn remove(&mut self, x: &T) -> Option<T> {
+        self.map.remove_entry(x).map(|(x, _)| x)
+    }
+
+    fn find(&self, x: &T) -> Option<&T> {
+        self.map.find(x).map(|(x, _)| x)
+    }
+}
+
+impl<T> Default for RedBlackTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> FromIterator<T> for RedBlackTree<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut s = Self::new();
+        for x in iter {
+            s.add(x);
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod test_redblack {
+    use super::*;
+    use crate::rng::Rng;
+
+    /// 親へのポインタ、赤が続かないこと、左傾、黒の高さが等しいことを確かめて黒の高さを返す
+    fn check<K: Ord, V>(m: &RedBlackMap<K, V>, u: usize) -> usize {
+        if u == NIL {
+            assert_eq!(m.colour(NIL), BLACK);
+            return 0;
+        }
+        let (l, r) = (m.left(u), m.right(u));
+        assert!(m.colour(u) == RED || m.colour(u) == BLACK);
+        if m.colour(u) == RED {
+            assert!(
+                m.colour(l) == BLACK && m.colour(r) == BLACK,
+                "two reds in a row"
+            );
+        }
+        if m.colour(l) == BLACK {
+            assert_eq!(m.colour(r), BLACK, "right-leaning red");
+        }
+        for &c in &[l, r] {
+            if c != NIL {
+                assert_eq!(m.parent(c), u);
+            }
+        }
+        if l != NIL {
+            assert!(m.entry(l).0 < m.entry(u).0);
+        }
+        if r != NIL {
+            assert!(m.entry(u).0 < m.entry(r).0);
+        }
+        let bl = check(m, l);
+        assert_eq!(bl, check(m, r), "black height differs");
+        bl + (m.colour(u) == BLACK) as usize
+    }
+
+    fn check_root<K: Ord, V>(m: &RedBlackMap<K, V>) {
+        if m.r != NIL {
+            assert_eq!(m.parent(m.r), NIL);
+            assert_eq!(m.colour(m.r), BLACK);
+        }
+        check(m, m.r);
+    }
+
+    #[test]
+    fn test_sset() {
+        let mut t = RedBlackTree::new();
+        for i in 0..1000 {
+            assert!(t.add(i));
+            check_root(&t.map);
+        }
+        assert!(!t.add(3));
+        assert_eq!(t.size(), 1000);
+        // 高さは2 log n以下
+        assert!(t.height() <= 20);
+
+        let mut rng = Rng::with_seed(1);
+        let mut removed = vec![false; 1000];
+        for _ in 0..700 {
+            let x = rng.gen_range(1000) as i32;
+            assert_eq!(t.remove(&x).is_some(), !removed[x as usize]);
+            removed[x as usize] = true;
+            check_root(&t.map);
+        }
+        let rest: Vec<_> = (0..1000).filter(|&x| !removed[x as usize]).collect();
+        assert_eq!(t.size(), rest.len());
+        assert!(t.iter().cloned().eq(rest.iter().cloned()));
+        assert_eq!(t.find(&-1), rest.first());
+        assert_eq!(t.find(&1000), None);
+
+        // 追加と削除を混ぜる(空いた位置も使い回す)
+        let mut model: std::collections::BTreeSet<i32> = rest.into_iter().collect();
+        for i in 0..3000 {
+            let x = rng.gen_range(1500) as i32;
+            if i % 3 == 2 {
+                assert_eq!(t.remove(&x), model.take(&x));
+            } else {
+                assert_eq!(t.add(x), model.insert(x));
+            }
+            check_root(&t.map);
+        }
+        assert!(t.iter().eq(model.iter()));
+        assert!(t.map.u.len() <= 1 + 1500);
+    }
+
+    #[test]
+    fn test_map() {
+        let mut m: RedBlackMap<_, _> = (0..100).map(|i| (i * 2, i)).collect();
+        assert_eq!(m.insert(10, 100), Some(5));
+        assert_eq!(m.get(&10), Some(&100));
+        assert_eq!(m.get(&11), None);
+        *m.get_mut(&0).unwrap() += 1;
+        assert_eq!(m.find(&11), Some((&12, &6)));
+        assert_eq!(m.remove(&12), Some(6));
+        assert_eq!(m.find(&11), Some((&14, &7)));
+        assert_eq!(m.remove(&12), None);
+        assert_eq!(m.size(), 99);
+        check_root(&m);
+        assert!(m
+            .keys()
+            .cloned()
+            .eq((0..200).step_by(2).filter(|&k| k != 12)));
+        assert_eq!(m.values().next(), Some(&1));
+    }
+}
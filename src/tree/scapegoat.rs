@@ -0,0 +1,178 @@
+use crate::tree::binary_tree::{self, Came, NodePtr};
+use crate::tree::bst::{BinarySearchTree, Iter};
+use crate::tree::SSet;
+
+/// log_{3/2} q
+fn log32(q: usize) -> f64 {
+    (q as f64).ln() / 1.5f64.ln()
+}
+
+/// ノードに釣り合いの情報を持たず、深すぎるノードができたら部分木を作り直す二分探索木
+/// bst: 中身の二分探索木
+/// q: nの上界(q/2 <= n <= q)
+#[derive(Debug)]
+pub struct ScapegoatTree<T> {
+    bst: BinarySearchTree<T>,
+    q: usize,
+}
+
+impl<T> ScapegoatTree<T> {
+    pub fn new() -> Self {
+        Self {
+            bst: BinarySearchTree::new(),
+            q: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bst.is_empty()
+    }
+
+    pub fn height(&self) -> isize {
+        self.bst.tree().height()
+    }
+
+    /// 昇順
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.bst.iter()
+    }
+
+    /// uの部分木を完全に釣り合った木に作り直す
+    fn rebuild(&mut self, u: NodePtr<T>) {
+        let mut a = Vec::with_capacity(binary_tree::size(Some(u)));
+        binary_tree::walk(Some(u), |w, came, _| {
+            let node = unsafe { w.as_ref() };
+            let visit = match came {
+                Came::Parent => node.left.is_none(),
+                Came::Left => true,
+                Came::Right => false,
+            };
+            if visit {
+                a.push(w);
+            }
+        });
+
+        let p = unsafe { u.as_ref().parent };
+        let mut r = build_balanced(&a);
+        unsafe {
+            r.as_mut().parent = p;
+            match p {
+                None => self.bst.tree.r = Some(r),
+                Some(mut p) => {
+                    if p.as_ref().left == Some(u) {
+                        p.as_mut().left = Some(r);
+                    } else {
+                        p.as_mut().right = Some(r);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 中間順に並んだノードaから高さが最小の木を組み立てて根を返す
+fn build_balanced<T>(a: &[NodePtr<T>]) -> NodePtr<T> {
+    let m = a.len() / 2;
+    let mut r = a[m];
+    let left = if m > 0 {
+        Some(build_balanced(&a[..m]))
+    } else {
+        None
+    };
+    let right = if m + 1 < a.len() {
+        Some(build_balanced(&a[m + 1..]))
+    } else {
+        None
+    };
+    unsafe {
+        for mut c in left.into_iter().chain(right) {
+            c.as_mut().parent = Some(r);
+        }
+        r.as_mut().left = left;
+        r.as_mut().right = right;
+    }
+    r
+}
+
+impl<T: Ord> SSet<T> for ScapegoatTree<T> {
+    fn size(&self) -> usize {
+        self.bst.size()
+    }
+
+    /// 入れたノードの深さがlog_{3/2} qを超えたら、
+    /// 親をたどって3 * size(w) > 2 * size(w.parent)となるw.parent(スケープゴート)を作り直す
+    fn add(&mut self, x: T) -> bool {
+        let u = match self.bst.add_node(x) {
+            Some(u) => u,
+            None => return false,
+        };
+        self.q += 1;
+
+        if binary_tree::depth(u) as f64 > log32(self.q) {
+            let mut w = u;
+            let mut p = unsafe { w.as_ref().parent.unwrap() };
+            let mut sw = 1;
+            let mut sp = binary_tree::size(Some(p));
+            while 3 * sw <= 2 * sp {
+                w = p;
+                p = unsafe { w.as_ref().parent.unwrap() };
+                sw = sp;
+                sp = binary_tree::size(Some(p));
+            }
+            self.rebuild(p);
+        }
+        true
+    }
+
+    /// 要素数がqの半分を下回ったら木全体を作り直す
+    fn remove(&mut self, x: &T) -> Option<T> {
+        let u = self.bst.find_node(x)?;
+        let (x, _) = self.bst.remove_node(u);
+        let n = self.bst.size();
+        if 2 * n < self.q {
+            if let Some(r) = self.bst.tree.r {
+                self.rebuild(r);
+            }
+            self.q = n;
+        }
+        Some(x)
+    }
+
+    fn find(&self, x: &T) -> Option<&T> {
+        self.bst.find(x)
+    }
+}
+
+impl<T> Default for ScapegoatTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test_scapegoat {
+    use super::*;
+
+    #[test]
+    fn test_sset() {
+        let mut t = ScapegoatTree::new();
+        for i in 0..1000 {
+            assert!(t.add(i));
+            assert!(t.height() as f64 <= log32(t.q));
+        }
+        assert!(!t.add(10));
+        assert_eq!(t.size(), 1000);
+        assert_eq!(t.bst.tree().size(), 1000);
+
+        for i in (0..1000).filter(|i| i % 4 != 0) {
+            assert_eq!(t.remove(&i), Some(i));
+            assert!(t.height() as f64 <= log32(t.q));
+        }
+        assert_eq!(t.remove(&1), None);
+        assert_eq!(t.size(), 250);
+        assert!(t.q < 2 * 250 + 1);
+        assert_eq!(t.find(&5), Some(&8));
+        assert_eq!(t.find(&997), None);
+        assert!(t.iter().cloned().eq((0..1000).step_by(4)));
+    }
+}
@@ -0,0 +1,307 @@
+use crate::array::allocate_heap;
+use crate::range::to_range;
+use std::ops::RangeBounds;
+
+/// 大きさmの配列を単位元で埋める
+fn filled<T: Default + Clone>(m: usize, e: &T) -> Box<[T]> {
+    let mut a = allocate_heap(m);
+    for x in a.iter_mut() {
+        *x = e.clone();
+    }
+    a
+}
+
+/// 結合的な演算opと単位元eについて、区間の積を求めるセグメント木
+/// 和、最小、最大、gcdなどをクロージャで渡す(交換法則はいらない)
+///
+/// a: 完全二分木を配列にしたもの、a[1]が根、a[m..]が葉
+/// n: 要素数
+/// m: 葉の数(n以上の2の冪)
+#[derive(Debug)]
+pub struct SegmentTree<T, Op> {
+    a: Box<[T]>,
+    n: usize,
+    m: usize,
+    e: T,
+    op: Op,
+}
+
+impl<T, Op> SegmentTree<T, Op>
+where
+    T: Default + Clone,
+    Op: Fn(&T, &T) -> T,
+{
+    /// すべて単位元で始める
+    pub fn new(n: usize, e: T, op: Op) -> Self {
+        let m = n.next_power_of_two();
+        Self {
+            a: filled(2 * m, &e),
+            n,
+            m,
+            e,
+            op,
+        }
+    }
+
+    pub fn from_slice(xs: &[T], e: T, op: Op) -> Self {
+        let mut t = Self::new(xs.len(), e, op);
+        t.a[t.m..t.m + xs.len()].clone_from_slice(xs);
+        for i in (1..t.m).rev() {
+            t.a[i] = (t.op)(&t.a[2 * i], &t.a[2 * i + 1]);
+        }
+        t
+    }
+
+    pub fn size(&self) -> usize {
+        self.n
+    }
+
+    pub fn get(&self, i: usize) -> &T {
+        assert!(i < self.n, "index out of bounds");
+        &self.a[self.m + i]
+    }
+
+    /// 葉を書き換えて根まで計算し直す
+    pub fn set(&mut self, i: usize, x: T) {
+        assert!(i < self.n, "index out of bounds");
+        let mut i = self.m + i;
+        self.a[i] = x;
+        while i > 1 {
+            i /= 2;
+            self.a[i] = (self.op)(&self.a[2 * i], &self.a[2 * i + 1]);
+        }
+    }
+
+    /// 範囲の要素を左から順にopでまとめたもの(空なら単位元)
+    /// 下から左右の端を狭めていき、左の結果と右の結果を別々に持つ
+    pub fn query<R: RangeBounds<usize>>(&self, range: R) -> T {
+        let (lo, hi) = to_range(range, self.n);
+        let (mut l, mut r) = (lo + self.m, hi + self.m);
+        let mut left = self.e.clone();
+        let mut right = self.e.clone();
+        while l < r {
+            if l % 2 == 1 {
+                left = (self.op)(&left, &self.a[l]);
+                l += 1;
+            }
+            if r % 2 == 1 {
+                r -= 1;
+                right = (self.op)(&self.a[r], &right);
+            }
+            l /= 2;
+            r /= 2;
+        }
+        (self.op)(&left, &right)
+    }
+}
+
+/// 区間への更新を遅延させるセグメント木
+/// 値はopとeのモノイド、更新はcomposeとidのモノイドで、
+/// apply(f, x, len)は長さlenの区間の積xに更新fを施した結果
+/// compose(f, g)はgの後にfを施す更新
+///
+/// a: 区間の積
+/// lazy: まだ子に伝えていない更新
+#[derive(Debug)]
+pub struct LazySegmentTree<T, U, Op, Apply, Compose> {
+    a: Box<[T]>,
+    lazy: Box<[U]>,
+    n: usize,
+    m: usize,
+    e: T,
+    id: U,
+    op: Op,
+    apply: Apply,
+    compose: Compose,
+}
+
+impl<T, U, Op, Apply, Compose> LazySegmentTree<T, U, Op, Apply, Compose>
+where
+    T: Default + Clone,
+    U: Default + Clone,
+    Op: Fn(&T, &T) -> T,
+    Apply: Fn(&U, &T, usize) -> T,
+    Compose: Fn(&U, &U) -> U,
+{
+    pub fn from_slice(xs: &[T], e: T, op: Op, id: U, apply: Apply, compose: Compose) -> Self {
+        let m = xs.len().next_power_of_two();
+        let mut t = Self {
+            a: filled(2 * m, &e),
+            lazy: filled(2 * m, &id),
+            n: xs.len(),
+            m,
+            e,
+            id,
+            op,
+            apply,
+            compose,
+        };
+        t.a[m..m + xs.len()].clone_from_slice(xs);
+        for i in (1..m).rev() {
+            t.a[i] = (t.op)(&t.a[2 * i], &t.a[2 * i + 1]);
+        }
+        t
+    }
+
+    pub fn size(&self) -> usize {
+        self.n
+    }
+
+    /// ノードuに更新fを施す、uの区間の長さはlen
+    fn apply_at(&mut self, u: usize, f: &U, len: usize) {
+        self.a[u] = (self.apply)(f, &self.a[u], len);
+        if u < self.m {
+            self.lazy[u] = (self.compose)(f, &self.lazy[u]);
+        }
+    }
+
+    /// uにたまっている更新を子に伝える
+    fn push(&mut self, u: usize, len: usize) {
+        let f = std::mem::replace(&mut self.lazy[u], self.id.clone());
+        self.apply_at(2 * u, &f, len / 2);
+        self.apply_at(2 * u + 1, &f, len / 2);
+    }
+
+    /// uは区間[ul, ur)を受け持つ
+    fn update_rec(&mut self, u: usize, ul: usize, ur: usize, lo: usize, hi: usize, f: &U) {
+        if hi <= ul || ur <= lo {
+            return;
+        }
+        if lo <= ul && ur <= hi {
+            self.apply_at(u, f, ur - ul);
+            return;
+        }
+        self.push(u, ur - ul);
+        let mid = (ul + ur) / 2;
+        self.update_rec(2 * u, ul, mid, lo, hi, f);
+        self.update_rec(2 * u + 1, mid, ur, lo, hi, f);
+        self.a[u] = (self.op)(&self.a[2 * u], &self.a[2 * u + 1]);
+    }
+
+    fn query_rec(&mut self, u: usize, ul: usize, ur: usize, lo: usize, hi: usize) -> T {
+        if hi <= ul || ur <= lo {
+            return self.e.clone();
+        }
+        if lo <= ul && ur <= hi {
+            return self.a[u].clone();
+        }
+        self.push(u, ur - ul);
+        let mid = (ul + ur) / 2;
+        let l = self.query_rec(2 * u, ul, mid, lo, hi);
+        let r = self.query_rec(2 * u + 1, mid, ur, lo, hi);
+        (self.op)(&l, &r)
+    }
+
+    /// 範囲のすべての要素に更新fを施す
+    pub fn update<R: RangeBounds<usize>>(&mut self, range: R, f: U) {
+        let (lo, hi) = to_range(range, self.n);
+        self.update_rec(1, 0, self.m, lo, hi, &f);
+    }
+
+    /// 範囲の要素を左から順にopでまとめたもの
+    /// 途中で更新を子に伝えるので&mut selfを取る
+    pub fn query<R: RangeBounds<usize>>(&mut self, range: R) -> T {
+        let (lo, hi) = to_range(range, self.n);
+        self.query_rec(1, 0, self.m, lo, hi)
+    }
+
+    pub fn get(&mut self, i: usize) -> T {
+        self.query(i..=i)
+    }
+}
+
+#[cfg(test)]
+mod test_segment_tree {
+    use super::*;
+    use crate::rng::Rng;
+
+    fn gcd(a: u64, b: u64) -> u64 {
+        if b == 0 {
+            a
+        } else {
+            gcd(b, a % b)
+        }
+    }
+
+    #[test]
+    fn test_query() {
+        let xs: Vec<i64> = (0..100).map(|i| (i * 37 % 101) - 50).collect();
+        let mut sum = SegmentTree::from_slice(&xs, 0, |a, b| a + b);
+        let min = SegmentTree::from_slice(&xs, i64::MAX, |a, b| *a.min(b));
+        let max = SegmentTree::from_slice(&xs, i64::MIN, |a, b| *a.max(b));
+        for &(lo, hi) in &[(0, 100), (3, 4), (10, 57), (99, 100), (20, 20)] {
+            let s = &xs[lo..hi];
+            assert_eq!(sum.query(lo..hi), s.iter().sum::<i64>());
+            assert_eq!(min.query(lo..hi), *s.iter().min().unwrap_or(&i64::MAX));
+            assert_eq!(max.query(lo..hi), *s.iter().max().unwrap_or(&i64::MIN));
+        }
+        sum.set(10, 1000);
+        assert_eq!(*sum.get(10), 1000);
+        assert_eq!(sum.query(..=10), xs[..10].iter().sum::<i64>() + 1000);
+
+        let g = SegmentTree::from_slice(&[12, 18, 24, 7, 14], 0, |a, b| gcd(*a, *b));
+        assert_eq!(g.query(..3), 6);
+        assert_eq!(g.query(3..), 7);
+        assert_eq!(g.query(..), 1);
+
+        // 交換できない演算(文字列の連結)も左から順にまとめる
+        let words: Vec<String> = "abcdefg".chars().map(|c| c.to_string()).collect();
+        let cat = SegmentTree::from_slice(&words, String::new(), |a, b| a.clone() + b);
+        assert_eq!(cat.query(1..6), "bcdef");
+    }
+
+    #[test]
+    fn test_lazy() {
+        // 区間に足して、区間の和と最小を求める
+        let xs: Vec<i64> = (0..50).collect();
+        let mut sum = LazySegmentTree::from_slice(
+            &xs,
+            0,
+            |a, b| a + b,
+            0,
+            |f, x, len| x + f * len as i64,
+            |f, g| f + g,
+        );
+        let mut min = LazySegmentTree::from_slice(
+            &xs,
+            i64::MAX,
+            |a, b| *a.min(b),
+            0,
+            |f, x, _| if *x == i64::MAX { *x } else { x + f },
+            |f, g| f + g,
+        );
+        let mut v = xs.clone();
+        let mut rng = Rng::with_seed(1);
+        for _ in 0..200 {
+            let lo = rng.gen_range(50);
+            let hi = lo + rng.gen_range(51 - lo);
+            let f = rng.gen_range(21) as i64 - 10;
+            sum.update(lo..hi, f);
+            min.update(lo..hi, f);
+            for x in &mut v[lo..hi] {
+                *x += f;
+            }
+            let (a, b) = (hi / 2, (hi + 50) / 2);
+            assert_eq!(sum.query(a..b), v[a..b].iter().sum::<i64>());
+            assert_eq!(min.query(a..b), *v[a..b].iter().min().unwrap_or(&i64::MAX));
+        }
+        assert_eq!(sum.get(7), v[7]);
+
+        // 区間への代入
+        let mut assign = LazySegmentTree::from_slice(
+            &xs,
+            0,
+            |a, b| a + b,
+            None,
+            |f: &Option<i64>, x, len| f.map_or(*x, |y| y * len as i64),
+            |f, g| f.or(*g),
+        );
+        assign.update(10..20, Some(1));
+        assign.update(15..25, Some(2));
+        assert_eq!(assign.query(10..25), 5 + 2 * 10);
+        assert_eq!(
+            assign.query(..),
+            (0..10).sum::<i64>() + 25 + (25..50).sum::<i64>()
+        );
+    }
+}
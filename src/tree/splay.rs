@@ -0,0 +1,467 @@
+use crate::tree::node::{BinaryNode, InOrder};
+use std::cmp::Ordering;
+use std::iter::FromIterator;
+
+type Tree<T> = Option<Box<Node<T>>>;
+
+/// size: 部分木のノード数
+#[derive(Debug)]
+struct Node<T> {
+    x: T,
+    size: usize,
+    left: Tree<T>,
+    right: Tree<T>,
+}
+
+fn size<T>(u: &Tree<T>) -> usize {
+    u.as_ref().map_or(0, |u| u.size)
+}
+
+impl<T> Node<T> {
+    fn new(x: T) -> Box<Self> {
+        Box::new(Self {
+            x,
+            size: 1,
+            left: None,
+            right: None,
+        })
+    }
+}
+
+impl<T> BinaryNode for Node<T> {
+    fn left(&self) -> &Tree<T> {
+        &self.left
+    }
+
+    fn right(&self) -> &Tree<T> {
+        &self.right
+    }
+
+    fn left_mut(&mut self) -> &mut Tree<T> {
+        &mut self.left
+    }
+
+    fn right_mut(&mut self) -> &mut Tree<T> {
+        &mut self.right
+    }
+
+    fn update(&mut self) {
+        self.size = 1 + size(&self.left) + size(&self.right);
+    }
+}
+
+/// トップダウンのスプレー
+/// 根から下りながら、目標より大きいノードを右の木に、小さいノードを左の木に外していき、
+/// 最後に止まったノードを根にして左右の木をつける
+/// cmp(y)は目標とyの比較で、最後に根にしたノードを返す
+fn splay<T, F>(mut t: Box<Node<T>>, cmp: F) -> Box<Node<T>>
+where
+    F: Fn(&T) -> Ordering,
+{
+    // 右の木に外したノード(上から順、それぞれの左の子が空いている)
+    let mut right = vec![];
+    // 左の木に外したノード(上から順、それぞれの右の子が空いている)
+    let mut left = vec![];
+    loop {
+        match cmp(&t.x) {
+            Ordering::Less => {
+                let mut l = match t.left.take() {
+                    Some(l) => l,
+                    None => break,
+                };
+                if cmp(&l.x) == Ordering::Less {
+                    // zig-zig: 先に右に回す
+                    t.left = l.right.take();
+                    t.update();
+                    l.right = Some(t);
+                    t = l;
+                    match t.left.take() {
+                        Some(ll) => {
+                            right.push(t);
+                            t = ll;
+                        }
+                        None => break,
+                    }
+                } else {
+                    right.push(t);
+                    t = l;
+                }
+            }
+            Ordering::Greater => {
+                let mut r = match t.right.take() {
+                    Some(r) => r,
+                    None => break,
+                };
+                if cmp(&r.x) == Ordering::Greater {
+                    t.right = r.left.take();
+                    t.update();
+                    r.left = Some(t);
+                    t = r;
+                    match t.right.take() {
+                        Some(rr) => {
+                            left.push(t);
+                            t = rr;
+                        }
+                        None => break,
+                    }
+                } else {
+                    left.push(t);
+                    t = r;
+                }
+            }
+            Ordering::Equal => break,
+        }
+    }
+
+    let mut acc = t.left.take();
+    for mut u in left.into_iter().rev() {
+        u.right = acc;
+        u.update();
+        acc = Some(u);
+    }
+    t.left = acc;
+
+    let mut acc = t.right.take();
+    for mut u in right.into_iter().rev() {
+        u.left = acc;
+        u.update();
+        acc = Some(u);
+    }
+    t.right = acc;
+
+    t.update();
+    t
+}
+
+/// 触ったノードを根に持ってくる自己調整二分探索木
+/// 最近使った要素ほど根に近いので、偏ったアクセスでは速い
+/// findも木を変えるので&mut selfを取り、SSetは実装しない
+/// r: 根
+#[derive(Debug)]
+pub struct SplayTree<T> {
+    r: Tree<T>,
+}
+
+impl<T> SplayTree<T> {
+    pub fn new() -> Self {
+        Self { r: None }
+    }
+
+    pub fn size(&self) -> usize {
+        size(&self.r)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.r.is_none()
+    }
+
+    /// 最後に触った要素
+    pub fn root(&self) -> Option<&T> {
+        self.r.as_ref().map(|r| &r.x)
+    }
+
+    /// 根から葉までの辺の数の最大値(空なら-1)
+    /// 一列に並ぶこともあるので再帰しない
+    pub fn height(&self) -> isize {
+        let mut h = -1;
+        let mut stack: Vec<(&Node<T>, isize)> = self.r.iter().map(|r| (&**r, 0)).collect();
+        while let Some((u, d)) = stack.pop() {
+            h = std::cmp::max(h, d);
+            for c in u.left.iter().chain(u.right.iter()) {
+                stack.push((c, d + 1));
+            }
+        }
+        h
+    }
+
+    /// 昇順
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            nodes: InOrder::new(self.r.as_deref()),
+        }
+    }
+
+    fn splay_by<F: Fn(&T) -> Ordering>(&mut self, cmp: F) {
+        if let Some(r) = self.r.take() {
+            self.r = Some(splay(r, cmp));
+        }
+    }
+
+    /// 最小の要素を根にして返す
+    pub fn first(&mut self) -> Option<&T> {
+        self.splay_by(|_| Ordering::Less);
+        self.root()
+    }
+
+    /// 最大の要素を根にして返す
+    pub fn last(&mut self) -> Option<&T> {
+        self.splay_by(|_| Ordering::Greater);
+        self.root()
+    }
+
+    /// otherの要素をすべて後ろにつなぐ
+    /// selfのすべての要素がotherのすべての要素より小さくなければならない
+    pub fn join(&mut self, mut other: Self)
+    where
+        T: Ord,
+    {
+        let b = match other.r.take() {
+            Some(b) => splay(b, |_| Ordering::Less),
+            None => return,
+        };
+        match self.r.take() {
+            None => self.r = Some(b),
+            Some(a) => {
+                // 最大の要素を根にすると右の子が空く
+                let mut a = splay(a, |_| Ordering::Greater);
+                assert!(
+                    a.x < b.x,
+                    "join: every element of self must be less than other"
+                );
+                a.right = Some(b);
+                a.update();
+                self.r = Some(a);
+            }
+        }
+    }
+}
+
+impl<T: Ord> SplayTree<T> {
+    fn splay(&mut self, x: &T) {
+        self.splay_by(|y| x.cmp(y));
+    }
+
+    /// x以上で最小の要素
+    /// xか、xを探して最後に見たノードが根に来る
+    pub fn find(&mut self, x: &T) -> Option<&T> {
+        self.splay(x);
+        let r = self.r.as_mut()?;
+        if r.x >= *x {
+            return Some(&r.x);
+        }
+        // 根はxより小さいので、答えは右の部分木の最小
+        let right = splay(r.right.take()?, |_| Ordering::Less);
+        r.right = Some(right);
+        r.right.as_ref().map(|u| &u.x)
+    }
+
+    pub fn contains(&mut self, x: &T) -> bool {
+        self.splay(x);
+        self.root() == Some(x)
+    }
+
+    /// xを根にする
+    pub fn add(&mut self, x: T) -> bool {
+        self.splay(&x);
+        let mut u = Node::new(x);
+        match self.r.take() {
+            None => {}
+            Some(mut r) => match u.x.cmp(&r.x) {
+                Ordering::Equal => {
+                    self.r = Some(r);
+                    return false;
+                }
+                Ordering::Less => {
+                    u.left = r.left.take();
+                    r.update();
+                    u.right = Some(r);
+                }
+                Ordering::Greater => {
+                    u.right = r.right.take();
+                    r.update();
+                    u.left = Some(r);
+                }
+            },
+        }
+        u.update();
+        self.r = Some(u);
+        true
+    }
+
+    /// xを根にして外し、左右の部分木をつなぐ
+    pub fn remove(&mut self, x: &T) -> Option<T> {
+        self.splay(x);
+        if self.root() != Some(x) {
+            return None;
+        }
+        let mut r = self.r.take().unwrap();
+        let right = r.right.take();
+        self.r = r.left.take();
+        self.join(Self { r: right });
+        Some(r.x)
+    }
+
+    /// xより大きい要素を取り出して新しいSplayTreeにする
+    pub fn split(&mut self, x: &T) -> Self {
+        self.splay(x);
+        let mut r = match self.r.take() {
+            Some(r) => r,
+            None => return Self::new(),
+        };
+        if r.x <= *x {
+            let right = r.right.take();
+            r.update();
+            self.r = Some(r);
+            Self { r: right }
+        } else {
+            self.r = r.left.take();
+            r.update();
+            Self { r: Some(r) }
+        }
+    }
+
+    /// xの深さ(木は変えない)
+    pub fn depth(&self, x: &T) -> Option<usize> {
+        let mut w = &self.r;
+        let mut d = 0;
+        while let Some(u) = w {
+            match x.cmp(&u.x) {
+                Ordering::Less => w = &u.left,
+                Ordering::Greater => w = &u.right,
+                Ordering::Equal => return Some(d),
+            }
+            d += 1;
+        }
+        None
+    }
+}
+
+impl<T> Default for SplayTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for SplayTree<T> {
+    /// 一列に並んだ木でも溢れないように、スタックで解放する
+    fn drop(&mut self) {
+        let mut stack: Vec<Box<Node<T>>> = self.r.take().into_iter().collect();
+        while let Some(mut u) = stack.pop() {
+            stack.extend(u.left.take());
+            stack.extend(u.right.take());
+        }
+    }
+}
+
+impl<T: Ord> FromIterator<T> for SplayTree<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut t = Self::new();
+        for x in iter {
+            t.add(x);
+        }
+        t
+    }
+}
+
+/// 中間順のイテレータ
+pub struct Iter<'a, T> {
+    nodes: InOrder<&'a Node<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.nodes.next().map(|u| &u.x)
+    }
+}
+
+#[cfg(test)]
+mod test_splay {
+    use super::*;
+    use crate::rng::Rng;
+
+    fn check<T: Ord>(u: &Tree<T>) -> usize {
+        match u {
+            None => 0,
+            Some(node) => {
+                if let Some(l) = &node.left {
+                    assert!(l.x < node.x);
+                }
+                if let Some(r) = &node.right {
+                    assert!(node.x < r.x);
+                }
+                let n = 1 + check(&node.left) + check(&node.right);
+                assert_eq!(n, node.size);
+                n
+            }
+        }
+    }
+
+    #[test]
+    fn test_add_find_remove() {
+        let mut t = SplayTree::new();
+        for i in (0..100).map(|i| i * 2) {
+            assert!(t.add(i));
+            assert_eq!(t.root(), Some(&i));
+        }
+        assert!(!t.add(10));
+        assert_eq!(t.size(), 100);
+        check(&t.r);
+
+        assert_eq!(t.find(&51), Some(&52));
+        assert_eq!(t.find(&52), Some(&52));
+        assert_eq!(t.root(), Some(&52));
+        assert_eq!(t.find(&199), None);
+        assert!(t.contains(&0));
+        assert!(!t.contains(&1));
+        assert_eq!(t.first(), Some(&0));
+        assert_eq!(t.last(), Some(&198));
+
+        for i in (0..200).step_by(4) {
+            assert_eq!(t.remove(&i), Some(i));
+        }
+        assert_eq!(t.remove(&0), None);
+        check(&t.r);
+        assert!(t.iter().cloned().eq((2..200).step_by(4)));
+    }
+
+    #[test]
+    fn test_split_join() {
+        let mut t: SplayTree<_> = (0..100).collect();
+        let u = t.split(&49);
+        assert_eq!((t.size(), u.size()), (50, 50));
+        assert!(t.iter().cloned().eq(0..50));
+        assert!(u.iter().cloned().eq(50..100));
+        check(&t.r);
+        check(&u.r);
+
+        t.join(u);
+        assert_eq!(t.size(), 100);
+        assert!(t.iter().cloned().eq(0..100));
+        let empty = t.split(&1000);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_working_set() {
+        // 昇順に入れると一列に並ぶが、再帰しないので溢れない
+        let n = 100_000;
+        let mut t: SplayTree<u64> = (0..n).collect();
+        assert_eq!(t.height(), n as isize - 1);
+
+        // 9割が16個の要素に集中するアクセス
+        let hot: Vec<u64> = (0..16).map(|i| i * 6007 + 13).collect();
+        let mut rng = Rng::with_seed(3);
+        let mut cost = 0;
+        let mut hot_accesses = 0;
+        for i in 0..20_000 {
+            let x = if i % 10 == 0 {
+                rng.gen_range(n as usize) as u64
+            } else {
+                hot[rng.gen_range(16)]
+            };
+            // 最初の一巡で一列の木が崩れるので、それ以降を数える
+            if i >= 1000 && i % 10 != 0 {
+                cost += t.depth(&x).unwrap();
+                hot_accesses += 1;
+            }
+            assert_eq!(t.find(&x), Some(&x));
+        }
+        for x in &hot {
+            assert!(t.depth(x).unwrap() < 24);
+        }
+        // 一様なアクセスならlog n(約17)かかるが、よく使う要素は根の近くにいる
+        let mean = cost as f64 / hot_accesses as f64;
+        assert!(mean < 8.0, "mean depth: {}", mean);
+    }
+}
@@ -0,0 +1,656 @@
+use crate::rng::Rng;
+use crate::tree::node::{rotate_left, rotate_right, BinaryNode, InOrder};
+use crate::tree::treap_core::{merge, trickle_down, TreapNode};
+use crate::tree::SSet;
+use std::cmp::Ordering;
+use std::ops::{Bound, RangeBounds};
+
+type Tree<T> = Option<Box<Node<T>>>;
+
+/// p: 優先度(小さいほど根に近い)
+/// size: 部分木のノード数
+#[derive(Debug)]
+struct Node<T> {
+    x: T,
+    p: u64,
+    size: usize,
+    left: Tree<T>,
+    right: Tree<T>,
+}
+
+impl<T> Node<T> {
+    fn new(x: T, p: u64) -> Self {
+        Self {
+            x,
+            p,
+            size: 1,
+            left: None,
+            right: None,
+        }
+    }
+}
+
+impl<T> BinaryNode for Node<T> {
+    fn left(&self) -> &Tree<T> {
+        &self.left
+    }
+
+    fn right(&self) -> &Tree<T> {
+        &self.right
+    }
+
+    fn left_mut(&mut self) -> &mut Tree<T> {
+        &mut self.left
+    }
+
+    fn right_mut(&mut self) -> &mut Tree<T> {
+        &mut self.right
+    }
+
+    fn update(&mut self) {
+        self.size = 1 + size(&self.left) + size(&self.right);
+    }
+}
+
+impl<T> TreapNode for Node<T> {
+    fn p(&self) -> u64 {
+        self.p
+    }
+}
+
+fn size<T>(u: &Tree<T>) -> usize {
+    u.as_ref().map_or(0, |u| u.size)
+}
+
+/// 普通の二分探索木と同じように葉に入れて、優先度が親より小さい間は回転で上げる
+fn add<T: Ord>(u: &mut Tree<T>, x: T, p: u64) -> bool {
+    let node = match u {
+        None => {
+            *u = Some(Box::new(Node::new(x, p)));
+            return true;
+        }
+        Some(node) => node,
+    };
+
+    let added = match x.cmp(&node.x) {
+        Ordering::Less => add(&mut node.left, x, p),
+        Ordering::Greater => add(&mut node.right, x, p),
+        Ordering::Equal => false,
+    };
+    if !added {
+        return false;
+    }
+
+    node.size += 1;
+    if node.left.as_ref().is_some_and(|l| l.p < node.p) {
+        rotate_right(node);
+    } else if node.right.as_ref().is_some_and(|r| r.p < node.p) {
+        rotate_left(node);
+    }
+    true
+}
+
+fn remove<T: Ord>(u: &mut Tree<T>, x: &T) -> Option<T> {
+    let node = u.as_mut()?;
+    let removed = match x.cmp(&node.x) {
+        Ordering::Less => remove(&mut node.left, x),
+        Ordering::Greater => remove(&mut node.right, x),
+        Ordering::Equal => return Some(trickle_down(u).x),
+    };
+    if removed.is_some() {
+        node.size -= 1;
+    }
+    removed
+}
+
+/// 小さい方からi番目(0から)を取り除く
+fn remove_at<T>(u: &mut Tree<T>, i: usize) -> Option<T> {
+    let node = u.as_mut()?;
+    let sl = size(&node.left);
+    let removed = match i.cmp(&sl) {
+        Ordering::Less => remove_at(&mut node.left, i),
+        Ordering::Greater => remove_at(&mut node.right, i - sl - 1),
+        Ordering::Equal => return Some(trickle_down(u).x),
+    };
+    if removed.is_some() {
+        node.size -= 1;
+    }
+    removed
+}
+
+/// x以下と、xより大きいものに分ける
+fn split_after<T: Ord>(u: Tree<T>, x: &T) -> (Tree<T>, Tree<T>) {
+    match u {
+        None => (None, None),
+        Some(mut node) => {
+            if node.x <= *x {
+                let (l, r) = split_after(node.right.take(), x);
+                node.right = l;
+                node.update();
+                (Some(node), r)
+            } else {
+                let (l, r) = split_after(node.left.take(), x);
+                node.left = r;
+                node.update();
+                (l, Some(node))
+            }
+        }
+    }
+}
+
+/// xより小さいものと、x以上に分ける
+fn split_before<T: Ord>(u: Tree<T>, x: &T) -> (Tree<T>, Tree<T>) {
+    match u {
+        None => (None, None),
+        Some(mut node) => {
+            if node.x < *x {
+                let (l, r) = split_before(node.right.take(), x);
+                node.right = l;
+                node.update();
+                (Some(node), r)
+            } else {
+                let (l, r) = split_before(node.left.take(), x);
+                node.left = r;
+                node.update();
+                (l, Some(node))
+            }
+        }
+    }
+}
+
+fn height<T>(u: &Tree<T>) -> isize {
+    match u {
+        None => -1,
+        Some(u) => 1 + std::cmp::max(height(&u.left), height(&u.right)),
+    }
+}
+
+/// 各ノードにランダムな優先度を持たせ、キーについては二分探索木、優先度についてはヒープにした木
+/// r: 根
+/// rng: 優先度を作る
+#[derive(Debug)]
+pub struct Treap<T> {
+    r: Tree<T>,
+    rng: Rng,
+}
+
+impl<T> Treap<T> {
+    pub fn new() -> Self {
+        Self::with_rng(Rng::new())
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_rng(Rng::with_seed(seed))
+    }
+
+    fn with_rng(rng: Rng) -> Self {
+        Self { r: None, rng }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.r.is_none()
+    }
+
+    pub fn height(&self) -> isize {
+        height(&self.r)
+    }
+
+    /// 昇順
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            nodes: InOrder::new(self.r.as_deref()),
+        }
+    }
+
+    /// otherの要素をすべて後ろにつなぐ
+    /// selfのすべての要素がotherのすべての要素より小さくなければならない
+    /// 確かめるのはselfの右端とotherの左端だけなので、全体で期待O(log n)
+    pub fn join(&mut self, mut other: Self)
+    where
+        T: Ord,
+    {
+        if let (Some(a), Some(b)) = (self.last(), other.first()) {
+            assert!(a < b, "join: every element of self must be less than other");
+        }
+        self.r = merge(self.r.take(), other.r.take());
+    }
+
+    /// xより大きい要素を取り出して新しいTreapにする
+    pub fn split(&mut self, x: &T) -> Self
+    where
+        T: Ord,
+    {
+        let (l, r) = split_after(self.r.take(), x);
+        self.r = l;
+        let mut t = Self::with_rng(self.rng.fork());
+        t.r = r;
+        t
+    }
+
+    /// 小さい方からk番目(0から)の要素
+    pub fn select(&self, mut k: usize) -> Option<&T> {
+        let mut w = &self.r;
+        while let Some(u) = w {
+            let sl = size(&u.left);
+            match k.cmp(&sl) {
+                Ordering::Less => w = &u.left,
+                Ordering::Greater => {
+                    k -= sl + 1;
+                    w = &u.right;
+                }
+                Ordering::Equal => return Some(&u.x),
+            }
+        }
+        None
+    }
+
+    /// Listと同じ位置での参照(selectと同じ)
+    pub fn get(&self, i: usize) -> Option<&T> {
+        self.select(i)
+    }
+
+    /// 位置iの要素を取り除く
+    pub fn remove_at(&mut self, i: usize) -> Option<T> {
+        remove_at(&mut self.r, i)
+    }
+
+    /// f(y)を満たす要素の数
+    /// fは小さい方の要素だけで成り立つ(単調な)条件でなければならない
+    fn count_while<F: Fn(&T) -> bool>(&self, f: F) -> usize {
+        let mut w = &self.r;
+        let mut r = 0;
+        while let Some(u) = w {
+            if f(&u.x) {
+                r += size(&u.left) + 1;
+                w = &u.right;
+            } else {
+                w = &u.left;
+            }
+        }
+        r
+    }
+
+    /// xより小さい要素の数
+    pub fn rank(&self, x: &T) -> usize
+    where
+        T: Ord,
+    {
+        self.count_while(|y| y < x)
+    }
+
+    /// lo以上hi未満の要素の数
+    pub fn count_range(&self, lo: &T, hi: &T) -> usize
+    where
+        T: Ord,
+    {
+        self.rank(hi).saturating_sub(self.rank(lo))
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.iter().next()
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        let mut u = self.r.as_ref()?;
+        while let Some(r) = &u.right {
+            u = r;
+        }
+        Some(&u.x)
+    }
+
+    pub fn pop_first(&mut self) -> Option<T> {
+        self.remove_at(0)
+    }
+
+    pub fn pop_last(&mut self) -> Option<T> {
+        let n = size(&self.r);
+        self.remove_at(n.checked_sub(1)?)
+    }
+
+    /// xより小さい要素のうち最大のもの
+    pub fn predecessor(&self, x: &T) -> Option<&T>
+    where
+        T: Ord,
+    {
+        let mut w = &self.r;
+        let mut z = None;
+        while let Some(u) = w {
+            if u.x < *x {
+                z = Some(&u.x);
+                w = &u.right;
+            } else {
+                w = &u.left;
+            }
+        }
+        z
+    }
+
+    /// xより大きい要素のうち最小のもの
+    pub fn successor(&self, x: &T) -> Option<&T>
+    where
+        T: Ord,
+    {
+        let mut w = &self.r;
+        let mut z = None;
+        while let Some(u) = w {
+            if u.x > *x {
+                z = Some(&u.x);
+                w = &u.left;
+            } else {
+                w = &u.right;
+            }
+        }
+        z
+    }
+
+    /// x以上の要素を取り出して新しいTreapにする(std::collections::BTreeSet::split_offと同じ)
+    pub fn split_off(&mut self, x: &T) -> Self
+    where
+        T: Ord,
+    {
+        let (l, r) = split_before(self.r.take(), x);
+        self.r = l;
+        let mut t = Self::with_rng(self.rng.fork());
+        t.r = r;
+        t
+    }
+
+    /// 範囲に入る要素を昇順に返す、後ろからもたどれる
+    /// stdと同じように..、a..、..=bなどを渡せる(始まりが終わりより後なら空)
+    pub fn range<R: RangeBounds<T>>(&self, range: R) -> Range<'_, T>
+    where
+        T: Ord,
+    {
+        let start = range.start_bound();
+        let end = range.end_bound();
+        let after_start = |y: &T| match start {
+            Bound::Included(lo) => y >= lo,
+            Bound::Excluded(lo) => y > lo,
+            Bound::Unbounded => true,
+        };
+        let before_end = |y: &T| match end {
+            Bound::Included(hi) => y <= hi,
+            Bound::Excluded(hi) => y < hi,
+            Bound::Unbounded => true,
+        };
+
+        // 範囲の前にある要素の数と、範囲の終わりまでの要素の数
+        let i = self.count_while(|y| !after_start(y));
+        let j = self.count_while(before_end);
+
+        let mut it = Range {
+            front: vec![],
+            back: vec![],
+            remaining: j.saturating_sub(i),
+        };
+        // 範囲の最初の要素までの道のうち、左に下りたノードを積む
+        let mut w = &self.r;
+        while let Some(u) = w {
+            if after_start(&u.x) {
+                it.front.push(&**u);
+                w = &u.left;
+            } else {
+                w = &u.right;
+            }
+        }
+        // 範囲の最後の要素までの道のうち、右に下りたノードを積む
+        let mut w = &self.r;
+        while let Some(u) = w {
+            if before_end(&u.x) {
+                it.back.push(&**u);
+                w = &u.right;
+            } else {
+                w = &u.left;
+            }
+        }
+        it
+    }
+}
+
+impl<T: Ord> SSet<T> for Treap<T> {
+    fn size(&self) -> usize {
+        size(&self.r)
+    }
+
+    fn add(&mut self, x: T) -> bool {
+        let p = self.rng.next_u64();
+        add(&mut self.r, x, p)
+    }
+
+    fn remove(&mut self, x: &T) -> Option<T> {
+        remove(&mut self.r, x)
+    }
+
+    fn find(&self, x: &T) -> Option<&T> {
+        let mut w = &self.r;
+        let mut z = None;
+        while let Some(u) = w {
+            match x.cmp(&u.x) {
+                Ordering::Less => {
+                    z = Some(&u.x);
+                    w = &u.left;
+                }
+                Ordering::Greater => w = &u.right,
+                Ordering::Equal => return Some(&u.x),
+            }
+        }
+        z
+    }
+}
+
+impl<T> Default for Treap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 中間順のイテレータ
+pub struct Iter<'a, T> {
+    nodes: InOrder<&'a Node<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.nodes.next().map(|u| &u.x)
+    }
+}
+
+/// Treap::rangeのイテレータ
+/// front: 前から見た中間順のスタック
+/// back: 後ろから見た中間順のスタック
+/// remaining: 残りの要素数(前後から来て行き過ぎないように数える)
+pub struct Range<'a, T> {
+    front: Vec<&'a Node<T>>,
+    back: Vec<&'a Node<T>>,
+    remaining: usize,
+}
+
+impl<'a, T> Iterator for Range<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let node = self.front.pop()?;
+        let mut u = &node.right;
+        while let Some(c) = u {
+            self.front.push(c);
+            u = &c.left;
+        }
+        Some(&node.x)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Range<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let node = self.back.pop()?;
+        let mut u = &node.left;
+        while let Some(c) = u {
+            self.back.push(c);
+            u = &c.right;
+        }
+        Some(&node.x)
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Range<'a, T> {}
+
+#[cfg(test)]
+mod test_treap {
+    use super::*;
+
+    /// キーについて二分探索木、優先度についてヒープ、sizeが正しいことを確かめる
+    fn check<T: Ord>(u: &Tree<T>) -> usize {
+        match u {
+            None => 0,
+            Some(node) => {
+                for c in [&node.left, &node.right].iter().copied().flatten() {
+                    assert!(node.p <= c.p);
+                }
+                if let Some(l) = &node.left {
+                    assert!(l.x < node.x);
+                }
+                if let Some(r) = &node.right {
+                    assert!(node.x < r.x);
+                }
+                let n = 1 + check(&node.left) + check(&node.right);
+                assert_eq!(n, node.size);
+                n
+            }
+        }
+    }
+
+    #[test]
+    fn test_sset() {
+        let mut t = Treap::with_seed(1);
+        for i in 0..10_000 {
+            assert!(t.add(i));
+        }
+        assert!(!t.add(5));
+        check(&t.r);
+        assert_eq!(t.size(), 10_000);
+        // 昇順に入れても高さはO(log n)
+        assert!(t.height() < 50, "height: {}", t.height());
+
+        for i in (0..10_000).step_by(2) {
+            assert_eq!(t.remove(&i), Some(i));
+        }
+        assert_eq!(t.remove(&0), None);
+        check(&t.r);
+        assert_eq!(t.find(&4), Some(&5));
+        assert_eq!(t.find(&9999), Some(&9999));
+        assert_eq!(t.find(&10_000), None);
+        assert!(t.iter().cloned().eq((1..10_000).step_by(2)));
+    }
+
+    #[test]
+    fn test_split_join() {
+        let mut t = Treap::with_seed(2);
+        for i in 0..100 {
+            t.add(i);
+        }
+        let mut u = t.split(&49);
+        check(&t.r);
+        check(&u.r);
+        assert!(t.iter().cloned().eq(0..50));
+        assert!(u.iter().cloned().eq(50..100));
+        assert_eq!(u.size(), 50);
+
+        // 範囲[50, 60)を消す
+        let rest = u.split(&59);
+        u.join(rest);
+        assert_eq!(u.size(), 50);
+        let mid = u.split(&59);
+        u = mid;
+        assert!(u.iter().cloned().eq(60..100));
+
+        t.join(u);
+        check(&t.r);
+        assert_eq!(t.size(), 90);
+        assert!(t.iter().cloned().eq((0..50).chain(60..100)));
+    }
+
+    #[test]
+    fn test_order_statistics() {
+        let mut t = Treap::with_seed(3);
+        for i in (0..1000).rev() {
+            t.add(i * 3);
+        }
+        for k in 0..1000 {
+            assert_eq!(t.select(k), Some(&(k * 3)));
+            assert_eq!(t.rank(&(k * 3)), k);
+            assert_eq!(t.rank(&(k * 3 + 1)), k + 1);
+        }
+        assert_eq!(t.get(1000), None);
+        assert_eq!(t.count_range(&10, &31), 7);
+        assert_eq!(t.count_range(&31, &10), 0);
+        assert_eq!(t.count_range(&0, &3000), 1000);
+
+        // 位置で消す
+        assert_eq!(t.remove_at(0), Some(0));
+        assert_eq!(t.remove_at(500), Some(1503));
+        assert_eq!(t.remove_at(998), None);
+        check(&t.r);
+        assert_eq!(t.size(), 998);
+        assert_eq!(t.get(500), Some(&1506));
+        assert_eq!(t.rank(&1506), 500);
+    }
+
+    #[test]
+    fn test_range() {
+        let mut t = Treap::with_seed(4);
+        for i in 0..50 {
+            t.add(i * 2);
+        }
+        assert!(t.range(10..20).cloned().eq((10..20).step_by(2)));
+        assert!(t.range(11..=20).cloned().eq((12..=20).step_by(2)));
+        assert!(t.range(..5).cloned().eq(vec![0, 2, 4]));
+        assert!(t.range(95..).cloned().eq(vec![96, 98]));
+        assert_eq!(t.range(..).len(), 50);
+        assert_eq!(
+            t.range((Bound::Included(30), Bound::Excluded(10))).next(),
+            None
+        );
+        assert_eq!(
+            t.range((Bound::Excluded(10), Bound::Excluded(16)))
+                .collect::<Vec<_>>(),
+            vec![&12, &14]
+        );
+
+        // 前後から交互に取っても重ならない
+        let mut r = t.range(10..=20);
+        assert_eq!(r.next_back(), Some(&20));
+        assert_eq!(r.next(), Some(&10));
+        assert_eq!(r.len(), 4);
+        assert!(r.rev().cloned().eq(vec![18, 16, 14, 12]));
+
+        assert_eq!(t.predecessor(&10), Some(&8));
+        assert_eq!(t.predecessor(&11), Some(&10));
+        assert_eq!(t.predecessor(&0), None);
+        assert_eq!(t.successor(&10), Some(&12));
+        assert_eq!(t.successor(&98), None);
+        assert_eq!((t.first(), t.last()), (Some(&0), Some(&98)));
+        assert_eq!((t.pop_first(), t.pop_last()), (Some(0), Some(98)));
+
+        let u = t.split_off(&50);
+        check(&t.r);
+        check(&u.r);
+        assert!(t.iter().cloned().eq((2..50).step_by(2)));
+        assert!(u.iter().cloned().eq((50..98).step_by(2)));
+
+        let mut e: Treap<i32> = Treap::with_seed(5);
+        assert_eq!((e.first(), e.last()), (None, None));
+        assert_eq!(e.pop_last(), None);
+        assert_eq!(e.range(..).next_back(), None);
+    }
+}
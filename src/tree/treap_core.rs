@@ -0,0 +1,82 @@
+use crate::tree::node::{rotate_left, rotate_right, BinaryNode};
+
+/// Treap、TreapList、Rope、IntervalTreeで共通に使うノードの操作
+/// p: 優先度(小さいほど根に近い)
+pub(crate) trait TreapNode: BinaryNode {
+    fn p(&self) -> u64;
+    /// まだ子に伝えていない操作を伝える
+    fn push(&mut self) {}
+}
+
+/// aの後ろにbをつなぐ
+/// 優先度の小さい方を根にして、下りる前に印を子に伝える
+pub(crate) fn merge<N: TreapNode>(a: Option<Box<N>>, b: Option<Box<N>>) -> Option<Box<N>> {
+    match (a, b) {
+        (None, b) => b,
+        (a, None) => a,
+        (Some(mut a), Some(mut b)) => {
+            if a.p() < b.p() {
+                a.push();
+                let r = a.right_mut().take();
+                *a.right_mut() = merge(r, Some(b));
+                a.update();
+                Some(a)
+            } else {
+                b.push();
+                let l = b.left_mut().take();
+                *b.left_mut() = merge(Some(a), l);
+                b.update();
+                Some(b)
+            }
+        }
+    }
+}
+
+/// 普通の二分探索木と同じようにwを葉に入れて、優先度が親より小さい間は回転で上げる
+/// go_left(u, w): wをuの左の部分木に入れるか
+pub(crate) fn add<N, F>(u: &mut Option<Box<N>>, w: Box<N>, go_left: &F)
+where
+    N: TreapNode,
+    F: Fn(&N, &N) -> bool,
+{
+    let node = match u {
+        None => {
+            *u = Some(w);
+            return;
+        }
+        Some(node) => node,
+    };
+    if go_left(node, &w) {
+        add(node.left_mut(), w, go_left);
+        if node.left().as_ref().is_some_and(|l| l.p() < node.p()) {
+            rotate_right(node);
+        }
+    } else {
+        add(node.right_mut(), w, go_left);
+        if node.right().as_ref().is_some_and(|r| r.p() < node.p()) {
+            rotate_left(node);
+        }
+    }
+    node.update();
+}
+
+/// uを回転で葉まで下ろしてから外して返す
+/// 優先度の小さい方の子を上げるのでヒープ順は崩れない
+pub(crate) fn trickle_down<N: TreapNode>(u: &mut Option<Box<N>>) -> Box<N> {
+    let node = u.as_mut().expect("trickle_down needs a node");
+    let left_up = match (node.left(), node.right()) {
+        (None, None) => return u.take().unwrap(),
+        (Some(_), None) => true,
+        (None, Some(_)) => false,
+        (Some(l), Some(r)) => l.p() < r.p(),
+    };
+    let w = if left_up {
+        rotate_right(node);
+        trickle_down(node.right_mut())
+    } else {
+        rotate_left(node);
+        trickle_down(node.left_mut())
+    };
+    node.update();
+    w
+}